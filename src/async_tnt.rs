@@ -0,0 +1,337 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::fs;
+use tokio::sync::RwLock;
+use tokio::task;
+
+use crate::audit::{AuditSink, Auditor};
+use crate::document::{Document, DuplicatePolicy};
+use crate::error::Error;
+use crate::storage::{FileStorage, MemoryStorage, WritableStorage};
+use crate::tnt::{self, TNT};
+
+/// Асинхронный доступ к TNT-файлу поверх `tokio::fs`.
+/// Каждый вызов читает файл один раз и выполняет ту же логику, что и синхронный `TNT`,
+/// над копией текста в памяти, поэтому результат совпадает с синхронным API.
+/// Записи внутри процесса упорядочены асинхронной блокировкой; клоны разделяют её.
+/// Между процессами и с синхронным `TNT` записи упорядочены той же блокировкой файла `{filename}.lock`.
+///
+/// Asynchronous access to a TNT file on top of `tokio::fs`.
+/// Each call reads the file once and runs the same logic as the synchronous `TNT`
+/// over an in-memory copy of the text, so the result matches the synchronous API.
+/// Writes within the process are ordered by an async lock; clones share it.
+/// Across processes and with the synchronous `TNT`, writes are ordered by the same `{filename}.lock` file lock.
+///
+/// # Example
+/// ```no_run
+/// # use tnt::AsyncTnt;
+/// # async fn run() -> Result<(), tnt::Error> {
+/// let tnt = AsyncTnt::open("app.tnt").await?;
+/// tnt.set("db", "port", 5432).await?;
+///
+/// let port = tnt.get("db", "port").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsyncTnt {
+    path: PathBuf,
+    storage: FileStorage,
+    duplicates: DuplicatePolicy,
+    audit: Option<Auditor>,
+    lock: Arc<RwLock<()>>,
+}
+
+impl AsyncTnt {
+    /// Открывает существующий файл.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к файлу
+    ///
+    /// Opens an existing file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let meta = fs::metadata(path).await.map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+
+        if !meta.is_file() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{}: not a file", path.display())).into());
+        }
+
+        Ok(Self::new(path))
+    }
+
+    /// Открывает файл, создавая пустой, если его нет.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к файлу
+    ///
+    /// Opens the file, creating an empty one if it does not exist.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    pub async fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .await
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+
+        Ok(Self::new(path))
+    }
+
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            storage: FileStorage::new(path),
+            duplicates: DuplicatePolicy::default(),
+            audit: None,
+            lock: Arc::new(RwLock::new(())),
+        }
+    }
+
+    /// Задаёт политику для повторяющихся секций и переменных (см. `TNT::duplicates`).
+    ///
+    /// # Аргументы
+    /// * `policy` - Политика дубликатов
+    ///
+    /// Sets the policy for repeated sections and variables (see `TNT::duplicates`).
+    ///
+    /// # Arguments
+    /// * `policy` - Duplicate policy
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Включает журнал аудита, как `TNT::audit`: записи отправляются до записи файла,
+    /// и ошибка приёмника отменяет изменение.
+    ///
+    /// # Аргументы
+    /// * `sink` - Приёмник записей (см. `AuditSink`, `JsonLinesSink`)
+    /// * `actor` - Кто вносит изменения через этот экземпляр
+    ///
+    /// Enables the audit log, like `TNT::audit`: records are sent before the file is written,
+    /// and a sink error cancels the change.
+    ///
+    /// # Arguments
+    /// * `sink` - Record sink (see `AuditSink`, `JsonLinesSink`)
+    /// * `actor` - Who makes changes through this instance
+    pub fn audit(mut self, sink: impl AuditSink + 'static, actor: &str) -> Self {
+        self.audit = Some(Auditor::new(Arc::new(sink), actor));
+        self
+    }
+
+    /// Путь к файлу.
+    ///
+    /// The path to the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Получает значение переменной с подстановкой ссылок `${...}`. См. `TNT::get`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Gets the value of a variable with `${...}` references substituted. See `TNT::get`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    pub async fn get<T: std::fmt::Display>(&self, key: &str, var: T) -> io::Result<String> {
+        self.inspect(|tnt| tnt.get(key, var)).await
+    }
+
+    /// Получает значение переменной как есть, без подстановки. См. `TNT::get_raw`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Gets the value of a variable as written, without substitution. See `TNT::get_raw`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    pub async fn get_raw<T: std::fmt::Display>(&self, key: &str, var: T) -> io::Result<String> {
+        self.inspect(|tnt| tnt.get_raw(key, var)).await
+    }
+
+    /// Загружает файл в `Document`.
+    ///
+    /// Loads the file into a `Document`.
+    pub async fn load(&self) -> Result<Document, Error> {
+        self.inspect(|tnt| tnt.load()).await
+    }
+
+    /// Устанавливает значение переменной, создавая секцию и переменную при необходимости. См. `TNT::set`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
+    ///
+    /// Sets the value of a variable, creating the section and variable as needed. See `TNT::set`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    pub async fn set<T: std::fmt::Display, V: std::fmt::Display>(&self, key: &str, var: T, val: V) -> Result<(), Error> {
+        self.modify(|tnt| tnt.set(key, var, val)).await
+    }
+
+    /// Удаляет переменную из секции ключа. См. `TNT::delete_var`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Deletes a variable from the key section. See `TNT::delete_var`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    pub async fn delete_var<T: std::fmt::Display>(&self, key: &str, var: T) -> io::Result<()> {
+        self.modify(|tnt| tnt.delete_var(key, var)).await
+    }
+
+    /// Удаляет секцию ключа целиком. См. `TNT::delete_key`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    ///
+    /// Deletes the whole key section. See `TNT::delete_key`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    pub async fn delete_key(&self, key: &str) -> io::Result<()> {
+        self.modify(|tnt| tnt.delete_key(key)).await
+    }
+
+    /// Атомарно записывает документ в файл, если файл не изменился после загрузки. См. `TNT::save`.
+    ///
+    /// # Аргументы
+    /// * `doc` - Документ для записи
+    ///
+    /// Atomically writes the document to the file if the file has not changed since loading. See `TNT::save`.
+    ///
+    /// # Arguments
+    /// * `doc` - Document to write
+    pub async fn save(&self, doc: &Document) -> Result<(), Error> {
+        self.modify(|tnt| tnt.save(doc)).await
+    }
+
+    /// Записывает документ, не проверяя чужие правки. См. `TNT::save_force`.
+    ///
+    /// # Аргументы
+    /// * `doc` - Документ для записи
+    ///
+    /// Writes the document without checking for foreign edits. See `TNT::save_force`.
+    ///
+    /// # Arguments
+    /// * `doc` - Document to write
+    pub async fn save_force(&self, doc: &Document) -> Result<(), Error> {
+        self.modify(|tnt| tnt.save_force(doc)).await
+    }
+
+    /// Экспортирует данные в TOML (`{filename}.toml`), скрывая секреты. См. `TNT::to_toml`.
+    ///
+    /// # Аргументы
+    /// * `filename` - Имя файла (без расширения)
+    ///
+    /// Exports the data to TOML (`{filename}.toml`), hiding secrets. See `TNT::to_toml`.
+    ///
+    /// # Arguments
+    /// * `filename` - File name (without extension)
+    pub async fn to_toml(&self, filename: &str) -> io::Result<()> {
+        let doc = self.inspect(|tnt| tnt.load()).await?;
+
+        fs::write(format!("{}.toml", filename), tnt::toml_text(&doc, false)).await
+    }
+
+    /// Экспортирует данные в JSON (`{filename}.json`), скрывая секреты. См. `TNT::to_json`.
+    ///
+    /// # Аргументы
+    /// * `filename` - Имя файла (без расширения)
+    ///
+    /// Exports the data to JSON (`{filename}.json`), hiding secrets. See `TNT::to_json`.
+    ///
+    /// # Arguments
+    /// * `filename` - File name (without extension)
+    pub async fn to_json(&self, filename: &str) -> io::Result<()> {
+        let doc = self.inspect(|tnt| tnt.load()).await?;
+
+        fs::write(format!("{}.json", filename), format!("{}\n", tnt::json_text(&doc, false))).await
+    }
+
+    fn memory(&self, text: &str) -> TNT<MemoryStorage> {
+        TNT::with_storage(MemoryStorage::new(text)).duplicates(self.duplicates).with_auditor(self.audit.clone())
+    }
+
+    async fn read_shared(&self) -> io::Result<String> {
+        let _guard = self.lock.read().await;
+
+        fs::read_to_string(&self.path).await
+    }
+
+    async fn inspect<R, E, F>(&self, f: F) -> Result<R, E>
+    where
+        E: From<io::Error>,
+        F: FnOnce(&TNT<MemoryStorage>) -> Result<R, E>,
+    {
+        let text = self.read_shared().await?;
+
+        f(&self.memory(&text))
+    }
+
+    async fn modify<R, E, F>(&self, f: F) -> Result<R, E>
+    where
+        E: From<io::Error>,
+        F: FnOnce(&TNT<MemoryStorage>) -> Result<R, E>,
+    {
+        let _guard = self.lock.write().await;
+
+        self.blocking(FileStorage::lock).await?;
+
+        let result = self.modify_locked(f).await;
+        self.storage.unlock()?;
+
+        result
+    }
+
+    async fn modify_locked<R, E, F>(&self, f: F) -> Result<R, E>
+    where
+        E: From<io::Error>,
+        F: FnOnce(&TNT<MemoryStorage>) -> Result<R, E>,
+    {
+        let before = fs::read_to_string(&self.path).await?;
+        let tnt = self.memory(&before);
+        let result = f(&tnt)?;
+        let after = tnt.storage().text();
+
+        if after != before {
+            self.blocking(move |storage| storage.write(&after)).await?;
+        }
+
+        Ok(result)
+    }
+
+    /// Выполняет блокирующую операцию над `FileStorage` в пуле потоков tokio.
+    async fn blocking<F>(&self, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&FileStorage) -> io::Result<()> + Send + 'static,
+    {
+        let storage = self.storage.clone();
+
+        task::spawn_blocking(move || f(&storage)).await.map_err(io::Error::other)?
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::diff::{self, Change};
+use crate::document::Document;
+use crate::json;
+
+/// Запись журнала аудита: одно изменение одной переменной.
+/// Значения секретных переменных заменены на `sha256:<hex>`.
+///
+/// An audit log record: one change of one variable.
+/// Values of secret variables are replaced with `sha256:<hex>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// Время изменения.
+    ///
+    /// The time of the change.
+    pub time: SystemTime,
+    /// Операция, выполнившая изменение (`add`, `set`, `delete_var`, `save`, `apply`, `migrate`, ...).
+    ///
+    /// The operation that made the change (`add`, `set`, `delete_var`, `save`, `apply`, `migrate`, ...).
+    pub operation: String,
+    /// Кто выполнил изменение (см. `TNT::audit`).
+    ///
+    /// Who made the change (see `TNT::audit`).
+    pub actor: String,
+    /// Секция (ключ).
+    ///
+    /// The section (key).
+    pub section: String,
+    /// Переменная; `None` для пустой секции.
+    ///
+    /// The variable; `None` for an empty section.
+    pub var: Option<String>,
+    /// Прежнее значение; `None`, если переменной не было.
+    ///
+    /// The old value; `None` if the variable did not exist.
+    pub old: Option<String>,
+    /// Новое значение; `None`, если переменная удалена.
+    ///
+    /// The new value; `None` if the variable was removed.
+    pub new: Option<String>,
+}
+
+impl AuditRecord {
+    /// Сериализует запись в JSON-объект в одну строку. Время записывается в формате RFC 3339 (UTC).
+    ///
+    /// Serializes the record as a single-line JSON object. The time is written as RFC 3339 (UTC).
+    pub fn to_json(&self) -> String {
+        let field = |val: &Option<String>| match val {
+            Some(val) => format!("\"{}\"", json::escape(val)),
+            None => "null".to_string(),
+        };
+
+        format!(
+            "{{\"timestamp\": \"{}\", \"operation\": \"{}\", \"actor\": \"{}\", \"section\": \"{}\", \"var\": {}, \"old\": {}, \"new\": {}}}",
+            rfc3339(self.time),
+            json::escape(&self.operation),
+            json::escape(&self.actor),
+            json::escape(&self.section),
+            field(&self.var),
+            field(&self.old),
+            field(&self.new),
+        )
+    }
+}
+
+/// Приёмник записей аудита. Реализован для замыканий `Fn(&AuditRecord) -> io::Result<()>`.
+///
+/// A sink for audit records. Implemented for `Fn(&AuditRecord) -> io::Result<()>` closures.
+///
+/// # Example
+/// ```
+/// # use std::sync::{Arc, Mutex};
+/// # use tnt::{AuditRecord, MemoryStorage, TNT};
+/// let records: Arc<Mutex<Vec<AuditRecord>>> = Arc::default();
+/// let sink = Arc::clone(&records);
+///
+/// let tnt = TNT::with_storage(MemoryStorage::new(""))
+///     .audit(move |record: &AuditRecord| Ok(sink.lock().unwrap().push(record.clone())), "deploy-bot");
+/// tnt.add("db", "port", 5432).unwrap();
+///
+/// let records = records.lock().unwrap();
+/// assert_eq!(records[0].operation, "add");
+/// assert_eq!(records[0].new.as_deref(), Some("5432"));
+/// ```
+pub trait AuditSink: Send + Sync {
+    /// Сохраняет одну запись.
+    ///
+    /// Stores one record.
+    fn record(&self, record: &AuditRecord) -> io::Result<()>;
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(&AuditRecord) -> io::Result<()> + Send + Sync,
+{
+    fn record(&self, record: &AuditRecord) -> io::Result<()> {
+        self(record)
+    }
+}
+
+impl<T: AuditSink + ?Sized> AuditSink for Arc<T> {
+    fn record(&self, record: &AuditRecord) -> io::Result<()> {
+        (**self).record(record)
+    }
+}
+
+/// Приёмник, дописывающий записи в файл по одному JSON-объекту на строку.
+///
+/// A sink that appends records to a file, one JSON object per line.
+///
+/// # Example
+/// ```no_run
+/// # use tnt::{JsonLinesSink, TNT};
+/// let tnt = TNT::open("prod.tnt").unwrap().audit(JsonLinesSink::open("audit.jsonl").unwrap(), "alice");
+/// tnt.edit("db", "port", 6432).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct JsonLinesSink {
+    file: Mutex<File>,
+}
+
+impl JsonLinesSink {
+    /// Открывает файл для дописывания, создавая его при необходимости.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к файлу журнала
+    ///
+    /// Opens the file for appending, creating it as needed.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the log file
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+
+        Ok(Self { file: Mutex::new(file) })
+    }
+}
+
+impl AuditSink for JsonLinesSink {
+    fn record(&self, record: &AuditRecord) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(file, "{}", record.to_json())?;
+
+        file.sync_data()
+    }
+}
+
+/// Приёмник и имя того, кто вносит изменения через данный экземпляр `TNT`.
+#[derive(Clone)]
+pub(crate) struct Auditor {
+    sink: Arc<dyn AuditSink>,
+    actor: String,
+}
+
+impl fmt::Debug for Auditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Auditor").field("actor", &self.actor).finish_non_exhaustive()
+    }
+}
+
+impl Auditor {
+    pub(crate) fn new(sink: Arc<dyn AuditSink>, actor: &str) -> Self {
+        Self { sink, actor: actor.to_string() }
+    }
+
+    /// Записывает по одной записи на каждую изменённую переменную между `before` и `after`.
+    pub(crate) fn record(&self, operation: &str, before: &str, after: &str) -> io::Result<()> {
+        let time = SystemTime::now();
+
+        let (Ok(before), Ok(after)) = (Document::parse(before), Document::parse(after)) else {
+            let record = AuditRecord {
+                time,
+                operation: operation.to_string(),
+                actor: self.actor.clone(),
+                section: String::new(),
+                var: None,
+                old: Some(digest(before)),
+                new: Some(digest(after)),
+            };
+
+            return self.sink.record(&record);
+        };
+
+        let shown = |key: &str, var: &str, val: &str| {
+            if before.is_secret(key, var) || after.is_secret(key, var) { digest(val) } else { val.to_string() }
+        };
+
+        let entry = |section: &str, var: Option<&str>, old: Option<String>, new: Option<String>| AuditRecord {
+            time,
+            operation: operation.to_string(),
+            actor: self.actor.clone(),
+            section: section.to_string(),
+            var: var.map(str::to_string),
+            old,
+            new,
+        };
+
+        let mut records = Vec::new();
+
+        for change in diff::diff_with_secrets(&before, &after).changes() {
+            match change {
+                Change::SectionAdded { key, entries } | Change::SectionRemoved { key, entries } if entries.is_empty() => {
+                    records.push(entry(key, None, None, None));
+                }
+                Change::SectionAdded { key, entries } => {
+                    for (var, val) in entries {
+                        records.push(entry(key, Some(var), None, Some(shown(key, var, val))));
+                    }
+                }
+                Change::SectionRemoved { key, entries } => {
+                    for (var, val) in entries {
+                        records.push(entry(key, Some(var), Some(shown(key, var, val)), None));
+                    }
+                }
+                Change::VarAdded { key, var, val } => records.push(entry(key, Some(var), None, Some(shown(key, var, val)))),
+                Change::VarRemoved { key, var, val } => records.push(entry(key, Some(var), Some(shown(key, var, val)), None)),
+                Change::VarChanged { key, var, old, new } => {
+                    records.push(entry(key, Some(var), Some(shown(key, var, old)), Some(shown(key, var, new))));
+                }
+                Change::VarMoved { from, to, var, val } => {
+                    records.push(entry(from, Some(var), Some(shown(from, var, val)), None));
+                    records.push(entry(to, Some(var), None, Some(shown(to, var, val))));
+                }
+            }
+        }
+
+        for record in &records {
+            self.sink.record(record)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Хеш значения для журнала: `sha256:<hex>`.
+fn digest(val: &str) -> String {
+    format!("sha256:{:x}", Sha256::digest(val.as_bytes()))
+}
+
+/// Время в формате RFC 3339 (UTC) с миллисекундами: `2024-01-31T12:00:00.000Z`.
+fn rfc3339(time: SystemTime) -> String {
+    humantime::format_rfc3339_millis(time.max(UNIX_EPOCH)).to_string()
+}
@@ -0,0 +1,280 @@
+use std::fmt;
+
+use crate::document::Document;
+use crate::json;
+use crate::secret;
+
+/// Одно смысловое изменение между двумя документами.
+///
+/// A single semantic change between two documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Секция добавлена вместе со своими переменными.
+    ///
+    /// The section was added together with its variables.
+    SectionAdded { key: String, entries: Vec<(String, String)> },
+    /// Секция удалена вместе со своими переменными.
+    ///
+    /// The section was removed together with its variables.
+    SectionRemoved { key: String, entries: Vec<(String, String)> },
+    /// Переменная добавлена в существующую секцию.
+    ///
+    /// The variable was added to an existing section.
+    VarAdded { key: String, var: String, val: String },
+    /// Переменная удалена из оставшейся секции.
+    ///
+    /// The variable was removed from a remaining section.
+    VarRemoved { key: String, var: String, val: String },
+    /// Значение переменной изменено.
+    ///
+    /// The value of the variable was changed.
+    VarChanged { key: String, var: String, old: String, new: String },
+    /// Переменная с тем же значением перенесена в другую секцию.
+    ///
+    /// The variable was moved to another section with the same value.
+    VarMoved { from: String, to: String, var: String, val: String },
+}
+
+/// Список изменений между двумя документами с текстовым и JSON-представлением.
+///
+/// The list of changes between two documents with text and JSON renderings.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Diff {
+    changes: Vec<Change>,
+}
+
+/// Сравнивает два документа на уровне секций и переменных.
+/// Значения секретных переменных в записях заменяются на `***`.
+///
+/// # Аргументы
+/// * `a` - Исходный документ
+/// * `b` - Новый документ
+///
+/// Compares two documents at the section and variable level.
+/// Values of secret variables are replaced with `***` in the records.
+///
+/// # Arguments
+/// * `a` - Original document
+/// * `b` - New document
+pub fn diff(a: &Document, b: &Document) -> Diff {
+    let is_secret = |key: &str, var: &str| a.is_secret(key, var) || b.is_secret(key, var);
+    let hide = |key: &str, var: &str, val: &mut String| {
+        if is_secret(key, var) {
+            *val = secret::REDACTED.to_string();
+        }
+    };
+
+    let mut diff = diff_with_secrets(a, b);
+
+    for change in diff.changes.iter_mut() {
+        match change {
+            Change::SectionAdded { key, entries } | Change::SectionRemoved { key, entries } => {
+                for (var, val) in entries.iter_mut() {
+                    hide(key, var, val);
+                }
+            }
+            Change::VarAdded { key, var, val } | Change::VarRemoved { key, var, val } => hide(key, var, val),
+            Change::VarChanged { key, var, old, new } => {
+                hide(key, var, old);
+                hide(key, var, new);
+            }
+            Change::VarMoved { from, to, var, val } => {
+                if is_secret(from, var) || is_secret(to, var) {
+                    *val = secret::REDACTED.to_string();
+                }
+            }
+        }
+    }
+
+    diff
+}
+
+/// Сравнивает два документа, как `diff`, но оставляет значения секретов открытыми.
+///
+/// Compares two documents like `diff`, but leaves secret values in plain text.
+pub fn diff_with_secrets(a: &Document, b: &Document) -> Diff {
+    let a_keys = keys(a);
+    let b_keys = keys(b);
+    let mut removed: Vec<(String, String, String)> = Vec::new();
+    let mut added: Vec<(String, String, String)> = Vec::new();
+
+    for key in a_keys.iter().filter(|k| b_keys.contains(k)) {
+        for (var, val) in a.entries(key) {
+            if b.get(key, var).is_none() {
+                removed.push((key.clone(), var.clone(), val.clone()));
+            }
+        }
+    }
+
+    for key in b_keys.iter().filter(|k| a_keys.contains(k)) {
+        for (var, val) in b.entries(key) {
+            if a.get(key, var).is_none() {
+                added.push((key.clone(), var.clone(), val.clone()));
+            }
+        }
+    }
+
+    let mut moves: Vec<(usize, usize)> = Vec::new();
+
+    for (i, (from, var, val)) in removed.iter().enumerate() {
+        let target = added
+            .iter()
+            .enumerate()
+            .find(|(j, (to, v, new))| to != from && v == var && new == val && !moves.iter().any(|(_, m)| m == j));
+
+        if let Some((j, _)) = target {
+            moves.push((i, j));
+        }
+    }
+
+    let mut changes = Vec::new();
+
+    for key in &a_keys {
+
+        if !b_keys.contains(key) {
+            changes.push(Change::SectionRemoved { key: key.clone(), entries: a.entries(key).to_vec() });
+            continue;
+        }
+
+        for (var, old) in a.entries(key) {
+            let (var, old) = (var.clone(), old.clone());
+
+            match b.get(key, &var) {
+                Some(new) if new != old => {
+                    changes.push(Change::VarChanged { key: key.clone(), var, old, new: new.to_string() });
+                }
+                Some(_) => {}
+                None => {
+                    let i = removed.iter().position(|(k, v, _)| k == key && *v == var);
+
+                    match moves.iter().find(|(m, _)| Some(*m) == i) {
+                        Some((_, j)) => changes.push(Change::VarMoved { from: key.clone(), to: added[*j].0.clone(), var, val: old }),
+                        None => changes.push(Change::VarRemoved { key: key.clone(), var, val: old }),
+                    }
+                }
+            }
+        }
+    }
+
+    for key in &b_keys {
+
+        if !a_keys.contains(key) {
+            changes.push(Change::SectionAdded { key: key.clone(), entries: b.entries(key).to_vec() });
+            continue;
+        }
+
+        for (j, (k, var, val)) in added.iter().enumerate() {
+            if k == key && !moves.iter().any(|(_, m)| *m == j) {
+                changes.push(Change::VarAdded { key: key.clone(), var: var.clone(), val: val.clone() });
+            }
+        }
+    }
+
+    Diff { changes }
+}
+
+fn keys(doc: &Document) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+
+    for section in doc.sections() {
+        if !keys.iter().any(|k| k == section.name()) {
+            keys.push(section.name().to_string());
+        }
+    }
+
+    keys
+}
+
+fn json_entries(entries: &[(String, String)]) -> String {
+    let fields: Vec<String> = entries
+        .iter()
+        .map(|(var, val)| format!("\"{}\": \"{}\"", json::escape(var), json::escape(val)))
+        .collect();
+
+    format!("{{{}}}", fields.join(", "))
+}
+
+impl Diff {
+    /// Список изменений в порядке следования секций.
+    ///
+    /// The list of changes in section order.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Возвращает `true`, если документы совпадают.
+    ///
+    /// Returns `true` if the documents are equal.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Текстовое представление: одно изменение на строку.
+    ///
+    /// Text rendering: one change per line.
+    pub fn to_text(&self) -> String {
+        let mut txt = String::new();
+
+        for change in &self.changes {
+            let line = match change {
+                Change::SectionAdded { key, entries } => format!("section `{}` added ({} variables)", key, entries.len()),
+                Change::SectionRemoved { key, entries } => format!("section `{}` removed ({} variables)", key, entries.len()),
+                Change::VarAdded { key, var, val } => format!("section `{}`: `{}` added = {}", key, var, val),
+                Change::VarRemoved { key, var, val } => format!("section `{}`: `{}` removed (was {})", key, var, val),
+                Change::VarChanged { key, var, old, new } => format!("section `{}`: `{}` changed {} -> {}", key, var, old, new),
+                Change::VarMoved { from, to, var, .. } => format!("`{}` moved from section `{}` to `{}`", var, from, to),
+            };
+
+            txt.push_str(&line);
+            txt.push('\n');
+        }
+
+        txt
+    }
+
+    /// JSON-представление: массив объектов с полем `op`.
+    ///
+    /// JSON rendering: an array of objects with an `op` field.
+    pub fn to_json(&self) -> String {
+        let records: Vec<String> = self
+            .changes
+            .iter()
+            .map(|change| match change {
+                Change::SectionAdded { key, entries } => {
+                    format!("{{\"op\": \"section_added\", \"section\": \"{}\", \"entries\": {}}}", json::escape(key), json_entries(entries))
+                }
+                Change::SectionRemoved { key, entries } => {
+                    format!("{{\"op\": \"section_removed\", \"section\": \"{}\", \"entries\": {}}}", json::escape(key), json_entries(entries))
+                }
+                Change::VarAdded { key, var, val } => format!(
+                    "{{\"op\": \"var_added\", \"section\": \"{}\", \"var\": \"{}\", \"value\": \"{}\"}}",
+                    json::escape(key), json::escape(var), json::escape(val)
+                ),
+                Change::VarRemoved { key, var, val } => format!(
+                    "{{\"op\": \"var_removed\", \"section\": \"{}\", \"var\": \"{}\", \"value\": \"{}\"}}",
+                    json::escape(key), json::escape(var), json::escape(val)
+                ),
+                Change::VarChanged { key, var, old, new } => format!(
+                    "{{\"op\": \"var_changed\", \"section\": \"{}\", \"var\": \"{}\", \"old\": \"{}\", \"new\": \"{}\"}}",
+                    json::escape(key), json::escape(var), json::escape(old), json::escape(new)
+                ),
+                Change::VarMoved { from, to, var, val } => format!(
+                    "{{\"op\": \"var_moved\", \"from\": \"{}\", \"to\": \"{}\", \"var\": \"{}\", \"value\": \"{}\"}}",
+                    json::escape(from), json::escape(to), json::escape(var), json::escape(val)
+                ),
+            })
+            .collect();
+
+        if records.is_empty() {
+            return "[]".to_string();
+        }
+
+        format!("[\n\t{}\n]", records.join(",\n\t"))
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_text())
+    }
+}
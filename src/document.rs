@@ -0,0 +1,1044 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::error::Error;
+use crate::secret;
+
+/// Секция документа: имя и переменные в порядке их следования.
+///
+/// A document section: its name and its variables in order.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct Section {
+    name: String,
+    entries: Vec<(String, String)>,
+    secrets: Vec<String>,
+}
+
+impl fmt::Debug for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<(&str, &str)> = self
+            .entries
+            .iter()
+            .map(|(var, val)| (var.as_str(), if self.is_secret(var) { secret::REDACTED } else { val.as_str() }))
+            .collect();
+
+        f.debug_struct("Section").field("name", &self.name).field("entries", &entries).finish()
+    }
+}
+
+impl Section {
+    pub(crate) fn new(name: &str) -> Self {
+        Self { name: name.trim().to_string(), entries: Vec::new(), secrets: Vec::new() }
+    }
+
+    pub(crate) fn mark_secret(&mut self, var: &str) {
+        if !self.secrets.iter().any(|v| v == var.trim()) {
+            self.secrets.push(var.trim().to_string());
+        }
+    }
+
+    /// Возвращает `true`, если переменная секретная: помечена `!secret` или названа по соглашению
+    /// (см. `secret::is_secret_name`).
+    ///
+    /// Returns `true` if the variable is secret: marked with `!secret` or named by convention
+    /// (see `secret::is_secret_name`).
+    pub fn is_secret(&self, var: &str) -> bool {
+        self.secrets.iter().any(|v| v == var.trim()) || secret::is_secret_name(var)
+    }
+
+    pub(crate) fn push(&mut self, var: &str, val: &str) {
+        self.entries.push((var.to_string(), val.to_string()));
+    }
+
+    /// Имя секции.
+    ///
+    /// The section name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Имя секции без профиля: `db` для `(db @prod)`.
+    ///
+    /// The section name without the profile: `db` for `(db @prod)`.
+    pub fn base_name(&self) -> &str {
+        self.name.split_once('@').map(|(base, _)| base.trim()).unwrap_or(&self.name)
+    }
+
+    /// Профиль секции: `prod` для `(db @prod)`, `None` для базовой секции.
+    ///
+    /// The profile of the section: `prod` for `(db @prod)`, `None` for a base section.
+    pub fn profile(&self) -> Option<&str> {
+        self.name.split_once('@').map(|(_, profile)| profile.trim())
+    }
+
+    /// Пары (переменная, значение) секции.
+    ///
+    /// The (variable, value) pairs of the section.
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    fn position(&self, var: &str) -> Option<usize> {
+        self.entries.iter().position(|(v, _)| v == var.trim())
+    }
+
+    /// Получает значение переменной.
+    ///
+    /// # Аргументы
+    /// * `var` - Имя переменной
+    ///
+    /// Gets the value of a variable.
+    ///
+    /// # Arguments
+    /// * `var` - Variable name
+    pub fn get(&self, var: &str) -> Option<&str> {
+        self.position(var).map(|pos| self.entries[pos].1.as_str())
+    }
+
+    /// Устанавливает значение переменной; новая переменная добавляется в конец секции.
+    /// Значение вида `!secret ...` помечает переменную как секретную.
+    ///
+    /// # Аргументы
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
+    ///
+    /// Sets the value of a variable; a new variable is appended to the end of the section.
+    /// A `!secret ...` value marks the variable as secret.
+    ///
+    /// # Arguments
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    ///
+    /// # Errors
+    /// `Error::Parse` if the name contains a line break, a bracket or `=`,
+    /// or the value contains a line break or an unpaired bracket.
+    pub fn set<T: std::fmt::Display, V: std::fmt::Display>(&mut self, var: T, val: V) -> Result<(), Error> {
+        let var_str = var.to_string().trim().to_string();
+        let val_str = val.to_string();
+        check_var(&var_str)?;
+        check_val(&val_str)?;
+
+        self.put(&var_str, &val_str);
+
+        Ok(())
+    }
+
+    /// Записывает переменную без проверки имени и значения: для значений, уже прошедших разбор.
+    pub(crate) fn put(&mut self, var: &str, val: &str) {
+        if secret::strip_marker(val).is_some() {
+            self.mark_secret(var);
+        }
+
+        let val = secret::plain(val).to_string();
+
+        match self.position(var) {
+            Some(pos) => self.entries[pos].1 = val,
+            None => self.entries.push((var.to_string(), val)),
+        }
+    }
+
+    /// Удаляет переменную. Возвращает её прежнее значение.
+    ///
+    /// # Аргументы
+    /// * `var` - Имя переменной
+    ///
+    /// Removes a variable. Returns its previous value.
+    ///
+    /// # Arguments
+    /// * `var` - Variable name
+    pub fn remove(&mut self, var: &str) -> Option<String> {
+        let pos = self.position(var)?;
+        self.secrets.retain(|v| v != var.trim());
+
+        Some(self.entries.remove(pos).1)
+    }
+
+    /// Итератор по парам (переменная, значение) в порядке их следования.
+    ///
+    /// An iterator over the (variable, value) pairs in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(var, val)| (var.as_str(), val.as_str()))
+    }
+
+    /// Количество переменных в секции.
+    ///
+    /// The number of variables in the section.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Возвращает `true`, если в секции нет переменных.
+    ///
+    /// Returns `true` if the section has no variables.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Вставляет переменную перед переменной `anchor`. Существующая переменная переносится на новое место.
+    ///
+    /// # Аргументы
+    /// * `anchor` - Переменная, перед которой выполняется вставка
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
+    ///
+    /// Inserts a variable before the `anchor` variable. An existing variable is moved to the new position.
+    ///
+    /// # Arguments
+    /// * `anchor` - Variable to insert before
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    ///
+    /// # Errors
+    /// `Error::VarNotFound` if `anchor` does not exist.
+    pub fn insert_before<V: std::fmt::Display>(&mut self, anchor: &str, var: &str, val: V) -> Result<(), Error> {
+        self.insert_near(anchor, var, val, 0)
+    }
+
+    /// Вставляет переменную после переменной `anchor`. Существующая переменная переносится на новое место.
+    ///
+    /// # Аргументы
+    /// * `anchor` - Переменная, после которой выполняется вставка
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
+    ///
+    /// Inserts a variable after the `anchor` variable. An existing variable is moved to the new position.
+    ///
+    /// # Arguments
+    /// * `anchor` - Variable to insert after
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    ///
+    /// # Errors
+    /// `Error::VarNotFound` if `anchor` does not exist.
+    pub fn insert_after<V: std::fmt::Display>(&mut self, anchor: &str, var: &str, val: V) -> Result<(), Error> {
+        self.insert_near(anchor, var, val, 1)
+    }
+
+    fn insert_near<V: std::fmt::Display>(&mut self, anchor: &str, var: &str, val: V, offset: usize) -> Result<(), Error> {
+
+        if self.position(anchor).is_none() {
+            return Err(Error::VarNotFound { key: self.name.clone(), var: anchor.trim().to_string() });
+        }
+
+        let val_str = val.to_string();
+        check_var(var.trim())?;
+        check_val(&val_str)?;
+
+        if anchor.trim() == var.trim() {
+            self.put(var.trim(), &val_str);
+            return Ok(());
+        }
+
+        if let Some(pos) = self.position(var) {
+            self.entries.remove(pos);
+        }
+
+        self.put(var.trim(), &val_str);
+
+        if let (Some(pos), Some(anchor_pos)) = (self.position(var), self.position(anchor)) {
+            let entry = self.entries.remove(pos);
+            self.entries.insert(anchor_pos + offset, entry);
+        }
+
+        Ok(())
+    }
+
+    /// Возвращает запись переменной для чтения или изменения на месте.
+    ///
+    /// # Аргументы
+    /// * `var` - Имя переменной
+    ///
+    /// # Пример
+    /// ```
+    /// # use tnt::Document;
+    /// let mut doc = Document::new();
+    /// doc.section_mut("db").entry("port").or_insert("5432");
+    ///
+    /// assert_eq!(doc.get("db", "port"), Some("5432"));
+    /// ```
+    ///
+    /// Returns the entry of a variable for in-place reading or modification.
+    ///
+    /// # Arguments
+    /// * `var` - Variable name
+    pub fn entry(&mut self, var: &str) -> Entry<'_> {
+        Entry { section: self, var: var.trim().to_string() }
+    }
+
+    pub(crate) fn render_entry(&self, var: &str, val: &str) -> String {
+        if self.secrets.iter().any(|v| v == var.trim()) {
+            format!("\t{}={} {}\n", var, secret::MARKER, val)
+        } else {
+            format!("\t{}={}\n", var, val)
+        }
+    }
+
+    fn render_entries(&self) -> String {
+        self.entries.iter().map(|(var, val)| self.render_entry(var, val)).collect()
+    }
+}
+
+/// Запись переменной в секции, полученная через `Section::entry`.
+///
+/// A variable entry in a section, obtained through `Section::entry`.
+#[derive(Debug)]
+pub struct Entry<'a> {
+    section: &'a mut Section,
+    var: String,
+}
+
+impl<'a> Entry<'a> {
+    /// Имя переменной.
+    ///
+    /// The variable name.
+    pub fn key(&self) -> &str {
+        &self.var
+    }
+
+    /// Возвращает значение, предварительно записав `default`, если переменной нет.
+    ///
+    /// Returns the value, first writing `default` if the variable is absent.
+    pub fn or_insert<V: std::fmt::Display>(self, default: V) -> &'a mut String {
+        self.or_insert_with(|| default)
+    }
+
+    /// Возвращает значение, предварительно записав результат `default`, если переменной нет.
+    ///
+    /// Returns the value, first writing the result of `default` if the variable is absent.
+    pub fn or_insert_with<V: std::fmt::Display, F: FnOnce() -> V>(self, default: F) -> &'a mut String {
+        let pos = match self.section.position(&self.var) {
+            Some(pos) => pos,
+            None => {
+                self.section.put(&self.var, &default().to_string());
+                self.section.entries.len() - 1
+            }
+        };
+
+        &mut self.section.entries[pos].1
+    }
+
+    /// Возвращает значение, предварительно записав пустую строку, если переменной нет.
+    ///
+    /// Returns the value, first writing an empty string if the variable is absent.
+    pub fn or_default(self) -> &'a mut String {
+        self.or_insert("")
+    }
+
+    /// Изменяет значение, если переменная существует.
+    ///
+    /// Modifies the value if the variable exists.
+    pub fn and_modify<F: FnOnce(&mut String)>(self, f: F) -> Self {
+        if let Some(pos) = self.section.position(&self.var) {
+            f(&mut self.section.entries[pos].1);
+        }
+
+        self
+    }
+}
+
+/// Правило обработки повторяющихся секций (несколько блоков `(db)`) и переменных внутри секции.
+///
+/// A policy for repeated sections (several `(db)` blocks) and repeated variables within a section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Повторы считаются ошибкой: `Error::KeyExists` или `Error::VarExists`.
+    ///
+    /// Repeats are an error: `Error::KeyExists` or `Error::VarExists`.
+    Error,
+    /// Действует первый блок секции и первое вхождение переменной, остальные игнорируются.
+    ///
+    /// The first block of a section and the first occurrence of a variable apply, the rest are ignored.
+    #[default]
+    FirstWins,
+    /// Действует последний блок секции и последнее вхождение переменной, остальные игнорируются.
+    ///
+    /// The last block of a section and the last occurrence of a variable apply, the rest are ignored.
+    LastWins,
+    /// Блоки секции объединяются в один на месте первого; повторяющаяся переменная остаётся на месте первого
+    /// вхождения со значением последнего.
+    ///
+    /// The blocks of a section are merged into one in place of the first; a repeated variable stays where it first
+    /// occurs and takes the value of the last occurrence.
+    Merge,
+}
+
+/// Разобранный TNT-документ, хранящийся в памяти.
+///
+/// A parsed TNT document held in memory.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Document {
+    version: Option<u32>,
+    includes: Vec<String>,
+    sections: Vec<Section>,
+    shadowed: Shadowed,
+    origin: Tracked,
+}
+
+/// Блоки и переменные, скрытые повторами при `dedup`, и место, куда их записывать:
+/// перед секциями (`LastWins`) или после них (`FirstWins`), чтобы они оставались скрытыми.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Shadowed {
+    before: bool,
+    sections: Vec<Section>,
+}
+
+/// Состояние хранилища, из которого загружен документ: отметка времени, хеш и сам текст.
+/// По нему `TNT::save` узнаёт, что файл изменили после загрузки.
+/// `Debug` не выводит текст: в нём могут быть секреты.
+#[derive(Clone)]
+pub(crate) struct Origin {
+    pub(crate) stamp: Option<(SystemTime, u64)>,
+    pub(crate) hash: u64,
+    pub(crate) text: Arc<str>,
+}
+
+impl fmt::Debug for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Origin").field("stamp", &self.stamp).field("hash", &self.hash).field("len", &self.text.len()).finish()
+    }
+}
+
+impl Origin {
+    pub(crate) fn new(stamp: Option<(SystemTime, u64)>, text: &str) -> Self {
+        Self { stamp, hash: Self::hash(text), text: Arc::from(text) }
+    }
+
+    pub(crate) fn hash(text: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+
+        hasher.finish()
+    }
+}
+
+/// Происхождение документа. Обновляется при сохранении через `&Document`,
+/// копируется при клонировании и не участвует в сравнении документов.
+#[derive(Debug, Default)]
+struct Tracked(Mutex<Option<Origin>>);
+
+impl Tracked {
+    fn get(&self) -> Option<Origin> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Clone for Tracked {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.get()))
+    }
+}
+
+impl PartialEq for Tracked {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Tracked {}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render_directives())?;
+
+        for section in &self.sections {
+            writeln!(f, "({}) {{", section.name)?;
+
+            for (var, val) in &section.entries {
+                let val = if section.is_secret(var) { secret::REDACTED } else { val.as_str() };
+                write!(f, "{}", section.render_entry(var, val))?;
+            }
+
+            writeln!(f, "}}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Document {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Error> {
+        Self::parse(text)
+    }
+}
+
+impl Document {
+    /// Создаёт пустой документ без версии.
+    ///
+    /// Creates an empty document without a version.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn origin(&self) -> Option<Origin> {
+        self.origin.get()
+    }
+
+    pub(crate) fn set_origin(&self, origin: Origin) {
+        *self.origin.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(origin);
+    }
+
+    fn is_ccf(text: &str) -> bool {
+        let count = |c: char| text.lines().filter(|line| line.contains(c)).count();
+
+        count('(') == count(')') && count('{') == count('}')
+    }
+
+    /// Разбирает документ из строки в формате TNT.
+    /// Повторяющиеся секции и переменные сохраняются как есть (см. `dedup`).
+    ///
+    /// # Аргументы
+    /// * `text` - Текст в формате TNT
+    ///
+    /// # Пример
+    /// ```
+    /// # use tnt::Document;
+    /// let doc = Document::parse("(db) {\n\tport=5432\n}\n").unwrap();
+    ///
+    /// assert_eq!(doc.get("db", "port"), Some("5432"));
+    /// assert_eq!(doc.render(), "(db) {\n\tport=5432\n}\n");
+    /// ```
+    ///
+    /// Parses a document from a string in TNT format.
+    /// Repeated sections and variables are kept as is (see `dedup`).
+    ///
+    /// # Arguments
+    /// * `text` - Text in TNT format
+    ///
+    /// # Errors
+    /// `Error::Integrity` if the brackets are unbalanced.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+
+        if !Self::is_ccf(text) {
+            return Err(Error::Integrity);
+        }
+
+        let mut doc = Document::new();
+        let mut is_key = false;
+
+        for line in text.lines() {
+            let fmt_line = line.trim();
+
+            if !is_key && fmt_line.starts_with('@') {
+                if let Some(version) = fmt_line.strip_prefix("@version") {
+                    doc.version = version.trim().parse().ok();
+                }
+
+                if let Some(path) = fmt_line.strip_prefix("@include") {
+                    doc.includes.push(path.trim().trim_matches('"').to_string());
+                }
+
+                continue;
+            }
+
+            if !is_key && line.contains('(') {
+                let cleaned_line = line.replace(['(', ')', '{'], "");
+                doc.sections.push(Section::new(&cleaned_line));
+                is_key = true;
+                continue;
+            }
+
+            if is_key && line.trim_start().starts_with('}') {
+                is_key = false;
+                continue;
+            }
+
+            if is_key && !fmt_line.is_empty() {
+                let (var, val) = fmt_line.split_once('=').unwrap_or((fmt_line, ""));
+
+                if let Some(section) = doc.sections.last_mut() {
+                    if secret::strip_marker(val).is_some() {
+                        section.mark_secret(var);
+                    }
+
+                    section.entries.push((var.to_string(), secret::plain(val).to_string()));
+                }
+            }
+        }
+
+        Ok(doc)
+    }
+
+    /// Читает и разбирает документ из любого источника `Read`: сокета, архива, буфера.
+    ///
+    /// # Аргументы
+    /// * `reader` - Источник текста в формате TNT
+    ///
+    /// Reads and parses a document from any `Read` source: a socket, an archive, a buffer.
+    ///
+    /// # Arguments
+    /// * `reader` - Source of text in TNT format
+    pub fn from_reader<R: std::io::Read>(mut reader: R) -> Result<Self, Error> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        Self::parse(&text)
+    }
+
+    /// Устраняет повторяющиеся секции и переменные по правилу `policy`.
+    /// При ошибке документ не изменяется. Скрытые повторы не теряются (см. `shadowed`).
+    ///
+    /// # Аргументы
+    /// * `policy` - Правило обработки повторов
+    ///
+    /// Resolves repeated sections and variables according to `policy`.
+    /// On error the document is left unchanged. The hidden repeats are not lost (see `shadowed`).
+    ///
+    /// # Arguments
+    /// * `policy` - Duplicate policy
+    ///
+    /// # Errors
+    /// `Error::KeyExists` or `Error::VarExists` for the first repeat under `DuplicatePolicy::Error`.
+    pub fn dedup(&mut self, policy: DuplicatePolicy) -> Result<(), Error> {
+        let mut sections: Vec<Section> = Vec::new();
+        let mut shadowed = Shadowed { before: policy == DuplicatePolicy::LastWins, sections: Vec::new() };
+
+        for section in self.sections.iter().cloned() {
+            let Some(pos) = sections.iter().position(|s| s.name == section.name) else {
+                sections.push(section);
+                continue;
+            };
+
+            match policy {
+                DuplicatePolicy::Error => return Err(Error::KeyExists(section.name)),
+                DuplicatePolicy::FirstWins => shadowed.sections.push(section),
+                DuplicatePolicy::LastWins => {
+                    shadowed.sections.push(sections.remove(pos));
+                    sections.push(section);
+                }
+                DuplicatePolicy::Merge => {
+                    sections[pos].entries.extend(section.entries);
+
+                    for var in &section.secrets {
+                        sections[pos].mark_secret(var);
+                    }
+                }
+            }
+        }
+
+        for section in &mut sections {
+            let mut entries: Vec<(String, String)> = Vec::new();
+            let mut hidden = Section::new(&section.name);
+
+            for (var, val) in section.entries.drain(..) {
+                let Some(pos) = entries.iter().position(|(v, _)| *v == var) else {
+                    entries.push((var, val));
+                    continue;
+                };
+
+                match policy {
+                    DuplicatePolicy::Error => return Err(Error::VarExists { key: section.name.clone(), var }),
+                    DuplicatePolicy::FirstWins => hidden.entries.push((var, val)),
+                    DuplicatePolicy::LastWins => {
+                        hidden.entries.push(entries.remove(pos));
+                        entries.push((var, val));
+                    }
+                    DuplicatePolicy::Merge => entries[pos].1 = val,
+                }
+            }
+
+            if !hidden.entries.is_empty() {
+                hidden.secrets = section.secrets.clone();
+                shadowed.sections.push(hidden);
+            }
+
+            section.entries = entries;
+        }
+
+        self.sections = sections;
+        self.shadowed = shadowed;
+
+        Ok(())
+    }
+
+    pub(crate) fn render_directives(&self) -> String {
+        let mut txt = String::new();
+
+        if let Some(version) = self.version {
+            txt.push_str(&format!("@version {}\n", version));
+        }
+
+        for path in &self.includes {
+            txt.push_str(&format!("@include \"{}\"\n", path));
+        }
+
+        txt
+    }
+
+    /// Выводит документ в формате TNT вместе с секретными значениями; результат снова разбирается `parse`.
+    /// `Display` выводит тот же текст, но со скрытыми секретами.
+    ///
+    /// Renders the document in TNT format including secret values; the result parses back with `parse`.
+    /// `Display` renders the same text with secrets redacted.
+    pub fn render(&self) -> String {
+        format!("{}{}", self.render_directives(), render_sections(&self.sections))
+    }
+
+    /// Повторы, скрытые последним вызовом `dedup` с `FirstWins` или `LastWins`: целые блоки секций
+    /// и отдельные переменные (как блоки из одной секции). `TNT::save` записывает их обратно после
+    /// действующих секций (`FirstWins`) или перед ними (`LastWins`), так что они остаются скрытыми,
+    /// но не пропадают из файла. Если удалить действующую секцию, при следующей загрузке станет виден её повтор.
+    ///
+    /// The repeats hidden by the last `dedup` call with `FirstWins` or `LastWins`: whole section blocks
+    /// and single variables (as one-section blocks). `TNT::save` writes them back after the effective
+    /// sections (`FirstWins`) or before them (`LastWins`), so they stay hidden but do not disappear
+    /// from the file. If the effective section is deleted, its repeat becomes visible on the next load.
+    pub fn shadowed(&self) -> &[Section] {
+        &self.shadowed.sections
+    }
+
+    /// Выводит документ, как `render`, вместе со скрытыми повторами (см. `shadowed`).
+    pub(crate) fn render_with_shadowed(&self) -> String {
+        let shadowed = render_sections(&self.shadowed.sections);
+        let sections = render_sections(&self.sections);
+
+        match self.shadowed.before {
+            true => format!("{}{}{}", self.render_directives(), shadowed, sections),
+            false => format!("{}{}{}", self.render_directives(), sections, shadowed),
+        }
+    }
+
+    /// Версия схемы документа (строка `@version N`), если она указана.
+    ///
+    /// The schema version of the document (the `@version N` line), if present.
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+
+    /// Устанавливает версию схемы документа.
+    ///
+    /// Sets the schema version of the document.
+    pub fn set_version(&mut self, version: u32) {
+        self.version = Some(version);
+    }
+
+    /// Пути из директив `@include` в порядке их следования.
+    ///
+    /// The paths of the `@include` directives in order.
+    pub fn includes(&self) -> &[String] {
+        &self.includes
+    }
+
+    /// Добавляет директиву `@include`. Путь может быть относительным и содержать `*` и `?` в имени файла.
+    ///
+    /// Adds an `@include` directive. The path may be relative and contain `*` and `?` in the file name.
+    pub fn add_include(&mut self, path: &str) {
+        if !self.includes.iter().any(|p| p == path) {
+            self.includes.push(path.to_string());
+        }
+    }
+
+    /// Все секции документа в порядке их следования.
+    ///
+    /// All sections of the document in order.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    pub(crate) fn push_section(&mut self, section: Section) {
+        self.sections.push(section);
+    }
+
+    fn find(&self, key: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == key.trim())
+    }
+
+    fn find_mut(&mut self, key: &str) -> Option<&mut Section> {
+        self.sections.iter_mut().find(|s| s.name == key.trim())
+    }
+
+    /// Возвращает секцию (ключ) по имени.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    ///
+    /// Returns a section (key) by name.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    pub fn section(&self, key: &str) -> Option<&Section> {
+        self.find(key)
+    }
+
+    /// Возвращает изменяемую секцию (ключ) по имени. Если секции нет, она будет создана в конце документа.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    ///
+    /// # Пример
+    /// ```
+    /// # use tnt::Document;
+    /// let mut doc = Document::new();
+    /// let db = doc.section_mut("db");
+    /// db.set("host", "localhost").unwrap();
+    /// db.insert_before("host", "port", 5432).unwrap();
+    ///
+    /// assert_eq!(db.iter().collect::<Vec<_>>(), [("port", "5432"), ("host", "localhost")]);
+    /// ```
+    ///
+    /// Returns a mutable section (key) by name. If the section does not exist, it is created at the end of the document.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    pub fn section_mut(&mut self, key: &str) -> &mut Section {
+        let pos = match self.sections.iter().position(|s| s.name == key.trim()) {
+            Some(pos) => pos,
+            None => {
+                self.sections.push(Section::new(key));
+                self.sections.len() - 1
+            }
+        };
+
+        &mut self.sections[pos]
+    }
+
+    /// Получает все пары (переменная, значение) в секции ключа.
+    /// Если секции нет, возвращает пустой срез.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    ///
+    /// Gets all (variable, value) pairs in the key section.
+    /// If the section does not exist, returns an empty slice.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    pub fn entries(&self, key: &str) -> &[(String, String)] {
+        self.find(key).map(|s| s.entries()).unwrap_or_default()
+    }
+
+    /// Получает значение переменной по ключу и имени переменной.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Gets the value of a variable by key and variable name.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    pub fn get(&self, key: &str, var: &str) -> Option<&str> {
+        self.find(key)?.get(var)
+    }
+
+    /// Устанавливает значение переменной, создавая секцию и переменную при необходимости.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
+    ///
+    /// Sets the value of a variable, creating the section and the variable as needed.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    ///
+    /// # Errors
+    /// `Error::Parse` if a name contains a line break or a bracket (or `=`, for the variable),
+    /// or the value contains a line break or an unpaired bracket.
+    pub fn set<T: std::fmt::Display, V: std::fmt::Display>(&mut self, key: &str, var: T, val: V) -> Result<(), Error> {
+        check_key(key.trim())?;
+
+        match self.find_mut(key) {
+            Some(section) => section.set(var, val),
+            None => {
+                let mut section = Section::new(key);
+                section.set(var, val)?;
+                self.sections.push(section);
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Устанавливает значение переменной и помечает её как секретную (`!secret`).
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
+    ///
+    /// Sets the value of a variable and marks it as secret (`!secret`).
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    ///
+    /// # Errors
+    /// `Error::Parse`, as in `set`.
+    pub fn set_secret<T: std::fmt::Display, V: std::fmt::Display>(&mut self, key: &str, var: T, val: V) -> Result<(), Error> {
+        self.set(key, var, format!("{} {}", secret::MARKER, val))
+    }
+
+    /// Записывает переменную без проверки, как `Section::put`.
+    pub(crate) fn put(&mut self, key: &str, var: &str, val: &str) {
+        self.section_mut(key).put(var.trim(), val);
+    }
+
+    /// Возвращает `true`, если переменная секретная (см. `Section::is_secret`).
+    ///
+    /// Returns `true` if the variable is secret (see `Section::is_secret`).
+    pub fn is_secret(&self, key: &str, var: &str) -> bool {
+        match self.find(key) {
+            Some(section) => section.is_secret(var),
+            None => secret::is_secret_name(var),
+        }
+    }
+
+    pub(crate) fn is_marked(&self, key: &str, var: &str) -> bool {
+        self.find(key).is_some_and(|s| s.secrets.iter().any(|v| v == var.trim()))
+    }
+
+    /// Удаляет переменную из секции ключа. Возвращает её прежнее значение.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Deletes a variable from the key section. Returns its previous value.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    pub fn delete_var(&mut self, key: &str, var: &str) -> Option<String> {
+        self.find_mut(key)?.remove(var)
+    }
+
+    /// Удаляет секцию (ключ) и все переменные внутри неё. Возвращает `true`, если секция была найдена.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    ///
+    /// Deletes a section (key) and all variables inside it. Returns `true` if the section was found.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    pub fn delete_key(&mut self, key: &str) -> bool {
+        let len = self.sections.len();
+        self.sections.retain(|s| s.name != key.trim());
+
+        self.sections.len() != len
+    }
+
+    /// Переименовывает секцию (ключ), сохраняя её положение. См. `TNT::rename_section`.
+    ///
+    /// Renames a section (key), keeping its position. See `TNT::rename_section`.
+    pub fn rename_section(&mut self, old: &str, new: &str) -> Result<(), Error> {
+
+        if self.find(old).is_none() {
+            return Err(Error::KeyNotFound(old.trim().to_string()));
+        }
+
+        if old.trim() == new.trim() {
+            return Ok(());
+        }
+
+        if self.find(new).is_some() {
+            return Err(Error::KeyExists(new.trim().to_string()));
+        }
+
+        for section in self.sections.iter_mut().filter(|s| s.name == old.trim()) {
+            section.name = new.trim().to_string();
+        }
+
+        Ok(())
+    }
+
+    /// Переименовывает переменную, сохраняя её значение и положение. См. `TNT::rename_var`.
+    ///
+    /// Renames a variable, keeping its value and position. See `TNT::rename_var`.
+    pub fn rename_var(&mut self, key: &str, old: &str, new: &str) -> Result<(), Error> {
+        let not_found = || Error::VarNotFound { key: key.trim().to_string(), var: old.trim().to_string() };
+        let section = self.find_mut(key).ok_or_else(not_found)?;
+        let pos = section.position(old).ok_or_else(not_found)?;
+
+        if old.trim() == new.trim() {
+            return Ok(());
+        }
+
+        if section.position(new).is_some() {
+            return Err(Error::VarExists { key: key.trim().to_string(), var: new.trim().to_string() });
+        }
+
+        section.entries[pos].0 = new.trim().to_string();
+
+        if let Some(secret) = section.secrets.iter_mut().find(|v| *v == old.trim()) {
+            *secret = new.trim().to_string();
+        }
+
+        Ok(())
+    }
+
+    /// Переносит переменную в конец другой секции. См. `TNT::move_var`.
+    ///
+    /// Moves a variable to the end of another section. See `TNT::move_var`.
+    pub fn move_var(&mut self, from: &str, to: &str, var: &str) -> Result<(), Error> {
+
+        if self.get(from, var).is_none() {
+            return Err(Error::VarNotFound { key: from.trim().to_string(), var: var.trim().to_string() });
+        }
+
+        if from.trim() == to.trim() {
+            return Ok(());
+        }
+
+        if self.get(to, var).is_some() {
+            return Err(Error::VarExists { key: to.trim().to_string(), var: var.trim().to_string() });
+        }
+
+        let is_marked = self.is_marked(from, var);
+
+        if let Some(val) = self.delete_var(from, var) {
+            self.put(to, var, &val);
+        }
+
+        if is_marked && let Some(section) = self.find_mut(to) {
+            section.mark_secret(var);
+        }
+
+        Ok(())
+    }
+}
+
+/// Проверяет имя секции: в нём не может быть переноса строки, круглых и фигурных скобок.
+pub(crate) fn check_key(key: &str) -> Result<(), Error> {
+
+    if key.contains(['\n', '\r', '(', ')', '{', '}']) {
+        return Err(Error::Parse(format!("invalid section name {:?}", key)));
+    }
+
+    Ok(())
+}
+
+/// Проверяет имя переменной: как имя секции, и ещё без `=`.
+pub(crate) fn check_var(var: &str) -> Result<(), Error> {
+
+    if var.contains(['\n', '\r', '(', ')', '{', '}', '=']) {
+        return Err(Error::Parse(format!("invalid variable name {:?}", var)));
+    }
+
+    Ok(())
+}
+
+/// Проверяет значение: без переноса строки и без непарных скобок, которые проверка целостности
+/// считает построчно. Парные скобки, как в ссылке `${db.host}`, допустимы.
+pub(crate) fn check_val(val: &str) -> Result<(), Error> {
+
+    if val.contains(['\n', '\r']) || val.contains('(') != val.contains(')') || val.contains('{') != val.contains('}') {
+        return Err(Error::Parse(format!("invalid value {:?}", val)));
+    }
+
+    Ok(())
+}
+
+fn render_sections(sections: &[Section]) -> String {
+    sections.iter().map(|s| format!("({}) {{\n{}}}\n", s.name, s.render_entries())).collect()
+}
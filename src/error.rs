@@ -0,0 +1,119 @@
+use std::fmt;
+
+use crate::diff::Diff;
+
+/// Ошибки операций над TNT-файлом.
+///
+/// Errors returned by operations on a TNT file.
+#[derive(Debug)]
+pub enum Error {
+    /// Ошибка ввода-вывода.
+    ///
+    /// An I/O error.
+    Io(std::io::Error),
+    /// Нарушена целостность файла (несбалансированные скобки).
+    ///
+    /// The file failed the integrity check (unbalanced brackets).
+    Integrity,
+    /// Секция (ключ) не найдена.
+    ///
+    /// The section (key) was not found.
+    KeyNotFound(String),
+    /// Секция (ключ) уже существует.
+    ///
+    /// The section (key) already exists.
+    KeyExists(String),
+    /// Переменная не найдена в секции.
+    ///
+    /// The variable was not found in the section.
+    VarNotFound { key: String, var: String },
+    /// Переменная уже существует в секции.
+    ///
+    /// The variable already exists in the section.
+    VarExists { key: String, var: String },
+    /// Не выполнено предусловие `test` патча. `None` означает отсутствие переменной,
+    /// значения секретных переменных заменены на `***`.
+    ///
+    /// A patch `test` precondition failed. `None` means the variable is absent,
+    /// values of secret variables are replaced with `***`.
+    TestFailed { key: String, var: String, expected: Option<String>, actual: Option<String> },
+    /// Ошибка разбора входных данных.
+    ///
+    /// The input could not be parsed.
+    Parse(String),
+    /// Ссылки `${...}` образуют цикл; перечислены переменные цикла.
+    ///
+    /// `${...}` references form a cycle; the variables of the cycle are listed.
+    Cycle(Vec<String>),
+    /// Ссылку `${...}` не удалось разрешить.
+    ///
+    /// A `${...}` reference could not be resolved.
+    Unresolved(String),
+    /// Файлы подключают друг друга по кругу через `@include`.
+    ///
+    /// Files include each other in a cycle through `@include`.
+    IncludeCycle(Vec<String>),
+    /// Индекс за пределами списка.
+    ///
+    /// The index is out of the list bounds.
+    IndexOutOfRange { key: String, var: String, index: usize, len: usize },
+    /// Ошибка шифрования или расшифровки секрета.
+    ///
+    /// A secret could not be encrypted or decrypted.
+    Crypto(String),
+    /// Файл изменён после загрузки документа; приведены изменения относительно загруженной версии.
+    ///
+    /// The file was changed after the document was loaded; the changes against the loaded version are listed.
+    Conflict(Diff),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Integrity => write!(f, "File integrity error!"),
+            Error::KeyNotFound(key) => write!(f, "The key '{}' was not found!", key),
+            Error::KeyExists(key) => write!(f, "A key named '{}' already exists!", key),
+            Error::VarNotFound { key, var } => write!(f, "The variable '{}' was not found in '{}'!", var, key),
+            Error::VarExists { key, var } => write!(f, "A variable named '{}' already exists in '{}'!", var, key),
+            Error::TestFailed { key, var, expected, actual } => {
+                write!(f, "Test failed for '{}.{}': expected {:?}, found {:?}!", key, var, expected, actual)
+            }
+            Error::Parse(msg) => write!(f, "Parse error: {}", msg),
+            Error::Cycle(cycle) => write!(f, "Reference cycle: {}", cycle.join(" -> ")),
+            Error::Unresolved(reference) => write!(f, "Unresolved reference '{}'!", reference),
+            Error::IncludeCycle(cycle) => write!(f, "Include cycle: {}", cycle.join(" -> ")),
+            Error::IndexOutOfRange { key, var, index, len } => {
+                write!(f, "Index {} is out of range for '{}.{}' of length {}!", index, key, var, len)
+            }
+            Error::Crypto(msg) => write!(f, "Secret error: {}", msg),
+            Error::Conflict(diff) => write!(f, "The file was changed after it was loaded:\n{}", diff),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            err => std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
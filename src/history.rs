@@ -0,0 +1,333 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::diff;
+use crate::document::Document;
+use crate::error::Error;
+use crate::storage::{FileStorage, Storage, WritableStorage};
+use crate::tnt::TNT;
+
+/// Сохранённая версия документа из истории.
+///
+/// A saved version of the document from the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    number: u64,
+    time: SystemTime,
+    summary: String,
+}
+
+impl Version {
+    /// Номер версии; более новые версии имеют большие номера.
+    ///
+    /// The version number; newer versions have larger numbers.
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// Когда версия была заменена новым содержимым.
+    ///
+    /// When the version was replaced by new content.
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// Краткое описание изменений, заменивших эту версию. Секреты скрыты.
+    ///
+    /// A short summary of the changes that replaced this version. Secrets are hidden.
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+}
+
+/// Файловое хранилище с историей. Перед каждой записью прежнее содержимое файла
+/// сохраняется в каталог `{filename}.history/` вместе со временем и описанием изменений,
+/// так что любую запись можно откатить. Секреты в снимках хранятся как есть.
+///
+/// File storage with history. Before every write the previous content of the file
+/// is kept in the `{filename}.history/` directory along with the time and a summary of the changes,
+/// so any write can be rolled back. Secrets are stored in the snapshots as is.
+///
+/// # Example
+/// ```no_run
+/// # use tnt::{HistoryStorage, TNT};
+/// let tnt = TNT::with_storage(HistoryStorage::new("app.tnt").keep(20));
+/// tnt.set("db", "port", 6432).unwrap();
+///
+/// let last = tnt.history().unwrap().pop().unwrap();
+/// println!("{}: {}", last.number(), last.summary());
+///
+/// tnt.rollback(last.number()).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct HistoryStorage {
+    base: FileStorage,
+    dir: PathBuf,
+    keep: Option<usize>,
+}
+
+impl HistoryStorage {
+    /// Создаёт хранилище с историей для указанного файла. Число версий не ограничено.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к файлу
+    ///
+    /// Creates storage with history for the given file. The number of versions is unlimited.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let base = FileStorage::new(path);
+        let dir = PathBuf::from(format!("{}.history", base.filename()));
+
+        Self { base, dir, keep: None }
+    }
+
+    /// Ограничивает историю последними `versions` версиями; более старые удаляются при записи.
+    /// `0` снимает ограничение.
+    ///
+    /// # Аргументы
+    /// * `versions` - Сколько версий хранить
+    ///
+    /// Limits the history to the last `versions` versions; older ones are removed on write.
+    /// `0` removes the limit.
+    ///
+    /// # Arguments
+    /// * `versions` - How many versions to keep
+    pub fn keep(mut self, versions: usize) -> Self {
+        self.keep = (versions > 0).then_some(versions);
+        self
+    }
+
+    /// Имя файла.
+    ///
+    /// The file name.
+    pub fn filename(&self) -> &str {
+        self.base.filename()
+    }
+
+    /// Каталог истории.
+    ///
+    /// The history directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Версии в истории, от старых к новым.
+    ///
+    /// The versions in the history, oldest first.
+    pub fn versions(&self) -> Result<Vec<Version>, Error> {
+        let mut versions = Vec::new();
+
+        for number in self.numbers()? {
+            let info = Document::parse(&fs::read_to_string(self.info_path(number))?)?;
+            let secs = info.get("version", "time").and_then(|t| t.parse().ok()).unwrap_or(0);
+
+            versions.push(Version {
+                number,
+                time: UNIX_EPOCH + Duration::from_secs(secs),
+                summary: info.get("version", "summary").unwrap_or_default().to_string(),
+            });
+        }
+
+        Ok(versions)
+    }
+
+    /// Текст указанной версии.
+    ///
+    /// # Аргументы
+    /// * `version` - Номер версии
+    ///
+    /// The text of the given version.
+    ///
+    /// # Arguments
+    /// * `version` - Version number
+    pub fn text(&self, version: u64) -> io::Result<String> {
+        fs::read_to_string(self.snapshot_path(version)).map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => io::Error::new(err.kind(), format!("version {} is not in the history", version)),
+            _ => err,
+        })
+    }
+
+    fn snapshot_path(&self, number: u64) -> PathBuf {
+        self.dir.join(format!("{:06}.tnt", number))
+    }
+
+    fn info_path(&self, number: u64) -> PathBuf {
+        self.dir.join(format!("{:06}.info", number))
+    }
+
+    fn numbers(&self) -> io::Result<Vec<u64>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut numbers = Vec::new();
+
+        for entry in entries {
+            let name = entry?.file_name();
+            let number = name.to_str().and_then(|n| n.strip_suffix(".info")).and_then(|n| n.parse::<u64>().ok());
+            numbers.extend(number);
+        }
+
+        numbers.sort_unstable();
+
+        Ok(numbers)
+    }
+
+    /// Сохраняет прежний текст как новую версию и возвращает её номер.
+    /// Снимок и описание получают права доступа основного файла: в снимке могут быть секреты.
+    fn record(&self, previous: &str, next: &str) -> io::Result<u64> {
+        fs::create_dir_all(&self.dir)?;
+        let permissions = fs::metadata(self.filename())?.permissions();
+
+        let mut number = self.numbers()?.last().map_or(1, |n| n + 1);
+
+        let mut file = loop {
+            match OpenOptions::new().write(true).create_new(true).open(self.snapshot_path(number)) {
+                Ok(file) => break file,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => number += 1,
+                Err(err) => return Err(err),
+            }
+        };
+
+        file.set_permissions(permissions.clone())?;
+        file.write_all(previous.as_bytes())?;
+        file.sync_all()?;
+
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let mut info = Document::new();
+        info.set("version", "time", secs)?;
+        info.set("version", "summary", summary(previous, next)).or_else(|_| info.set("version", "summary", "changes not shown"))?;
+
+        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(self.info_path(number))?;
+        file.set_permissions(permissions)?;
+        file.write_all(info.render().as_bytes())?;
+
+        Ok(number)
+    }
+
+    fn remove(&self, number: u64) -> io::Result<()> {
+        fs::remove_file(self.info_path(number))?;
+        fs::remove_file(self.snapshot_path(number))
+    }
+
+    fn prune(&self) -> io::Result<()> {
+        let Some(keep) = self.keep else {
+            return Ok(());
+        };
+
+        let numbers = self.numbers()?;
+
+        for number in &numbers[..numbers.len().saturating_sub(keep)] {
+            self.remove(*number)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Storage for HistoryStorage {
+    fn read(&self) -> io::Result<String> {
+        self.base.read()
+    }
+
+    fn stamp(&self) -> io::Result<Option<(SystemTime, u64)>> {
+        self.base.stamp()
+    }
+}
+
+impl WritableStorage for HistoryStorage {
+    fn write(&self, text: &str) -> io::Result<()> {
+        let previous = match self.base.read() {
+            Ok(previous) => previous,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return self.base.write(text),
+            Err(err) => return Err(err),
+        };
+
+        if previous == text {
+            return Ok(());
+        }
+
+        let number = self.record(&previous, text)?;
+
+        if let Err(err) = self.base.write(text) {
+            self.remove(number)?;
+            return Err(err);
+        }
+
+        self.prune()
+    }
+
+    fn lock(&self) -> io::Result<()> {
+        self.base.lock()
+    }
+
+    fn unlock(&self) -> io::Result<()> {
+        self.base.unlock()
+    }
+}
+
+impl TNT<HistoryStorage> {
+    /// Версии в истории, от старых к новым. См. `HistoryStorage`.
+    ///
+    /// The versions in the history, oldest first. See `HistoryStorage`.
+    pub fn history(&self) -> Result<Vec<Version>, Error> {
+        self.storage().versions()
+    }
+
+    /// Загружает указанную версию из истории.
+    ///
+    /// # Аргументы
+    /// * `version` - Номер версии
+    ///
+    /// Loads the given version from the history.
+    ///
+    /// # Arguments
+    /// * `version` - Version number
+    pub fn show(&self, version: u64) -> Result<Document, Error> {
+        let mut doc = Document::parse(&self.storage().text(version)?)?;
+        doc.dedup(self.duplicate_policy())?;
+
+        Ok(doc)
+    }
+
+    /// Возвращает файл к указанной версии. Текущее содержимое при этом само попадает в историю,
+    /// поэтому откат тоже можно откатить.
+    ///
+    /// # Аргументы
+    /// * `version` - Номер версии
+    ///
+    /// Restores the file to the given version. The current content goes into the history itself,
+    /// so the rollback can be rolled back too.
+    ///
+    /// # Arguments
+    /// * `version` - Version number
+    pub fn rollback(&self, version: u64) -> Result<(), Error> {
+        self.locked(|| {
+            let text = self.storage().text(version)?;
+            self.commit("rollback", &text)?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Описание изменений одной строкой: смысловые изменения через `; `, секреты скрыты.
+fn summary(previous: &str, next: &str) -> String {
+    let (Ok(previous), Ok(next)) = (Document::parse(previous), Document::parse(next)) else {
+        return "unparsable content".to_string();
+    };
+
+    let changes = diff::diff(&previous, &next);
+
+    if changes.is_empty() {
+        return "no semantic changes".to_string();
+    }
+
+    changes.to_text().lines().collect::<Vec<_>>().join("; ")
+}
@@ -0,0 +1,270 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audit::Auditor;
+use crate::document::{Document, DuplicatePolicy, Section};
+use crate::error::Error;
+use crate::secret;
+use crate::tnt::TNT;
+
+/// Документ, собранный из основного файла и всех файлов, подключённых через `@include`.
+///
+/// Правила сборки:
+/// * относительные пути разрешаются от каталога подключающего файла;
+/// * `*` и `?` допустимы в имени файла (`conf.d/*.tnt`), совпадения берутся в алфавитном порядке;
+/// * подключённые файлы читаются раньше самого файла, независимо от положения директивы;
+/// * файл, подключённый несколько раз (например, общий для двух подключений), читается один раз, при первом подключении;
+/// * одноимённые секции из разных файлов объединяются, для одной переменной побеждает последнее чтение,
+///   то есть основной файл переопределяет подключённые, а поздние подключения — ранние.
+///
+/// A document assembled from the main file and every file pulled in with `@include`.
+///
+/// Assembly rules:
+/// * relative paths are resolved against the directory of the including file;
+/// * `*` and `?` are allowed in the file name (`conf.d/*.tnt`), matches are taken in alphabetical order;
+/// * included files are read before the including file itself, wherever the directive appears;
+/// * a file included several times (for example, shared by two includes) is read once, at its first include;
+/// * sections with the same name in several files are merged, and for a single variable the last read wins,
+///   so the main file overrides its includes and later includes override earlier ones.
+///
+/// `@include` действует только через `TNT::compose`: `TNT::get`, `TNT::load` и остальные методы `TNT`
+/// работают с одним файлом и директиву пропускают.
+///
+/// `@include` only takes effect through `TNT::compose`: `TNT::get`, `TNT::load` and the other `TNT` methods
+/// work with a single file and skip the directive.
+///
+/// # Example
+/// ```no_run
+/// # use tnt::TNT;
+/// let tnt = TNT::open("app.tnt").unwrap();
+/// let mut config = tnt.compose().unwrap();
+///
+/// println!("{:?}", config.get("db", "port"));
+/// config.set("db", "port", 6432).unwrap(); // written to the file that defines db.port
+/// ```
+#[derive(Debug, Clone)]
+pub struct Composed {
+    files: Vec<(PathBuf, Document)>,
+    merged: Document,
+    origins: Vec<(String, String, usize)>,
+    duplicates: DuplicatePolicy,
+    audit: Option<Auditor>,
+}
+
+impl TNT {
+    /// Собирает документ из файла и всех его `@include`. Каждый файл читается и записывается
+    /// по правилу `duplicates` этого `TNT`.
+    /// Если включён аудит (`TNT::audit`), изменения через `Composed` тоже записываются, с операциями `set` и `delete_var`.
+    ///
+    /// Assembles a document from the file and all of its `@include`s. Every file is read and written
+    /// under the `duplicates` policy of this `TNT`.
+    /// If the audit is enabled (`TNT::audit`), changes made through `Composed` are recorded too, with the `set` and `delete_var` operations.
+    ///
+    /// # Errors
+    /// `Error::IncludeCycle` if the files include each other in a cycle.
+    pub fn compose(&self) -> Result<Composed, Error> {
+        let mut files = Vec::new();
+        load(Path::new(self.filename()), self.duplicate_policy(), &mut Vec::new(), &mut Vec::new(), &mut files)?;
+
+        let mut composed = Composed {
+            files,
+            merged: Document::new(),
+            origins: Vec::new(),
+            duplicates: self.duplicate_policy(),
+            audit: self.auditor().cloned(),
+        };
+        composed.rebuild();
+
+        Ok(composed)
+    }
+}
+
+fn load(
+    path: &Path,
+    policy: DuplicatePolicy,
+    stack: &mut Vec<PathBuf>,
+    seen: &mut Vec<PathBuf>,
+    files: &mut Vec<(PathBuf, Document)>,
+) -> Result<(), Error> {
+    let canonical = fs::canonicalize(path)?;
+
+    if let Some(pos) = stack.iter().position(|p| *p == canonical) {
+        let mut cycle: Vec<String> = stack[pos..].iter().map(|p| p.display().to_string()).collect();
+        cycle.push(canonical.display().to_string());
+        return Err(Error::IncludeCycle(cycle));
+    }
+
+    if seen.contains(&canonical) {
+        return Ok(());
+    }
+
+    seen.push(canonical.clone());
+
+    let doc = TNT::open_read_only(path)?.duplicates(policy).load()?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    stack.push(canonical);
+
+    for pattern in doc.includes() {
+        for include in expand(&dir.join(pattern))? {
+            load(&include, policy, stack, seen, files)?;
+        }
+    }
+
+    stack.pop();
+    files.push((path.to_path_buf(), doc));
+
+    Ok(())
+}
+
+fn expand(pattern: &Path) -> Result<Vec<PathBuf>, Error> {
+    let name = pattern.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+    if !name.contains(['*', '?']) {
+        return Ok(vec![pattern.to_path_buf()]);
+    }
+
+    let dir = pattern.parent().unwrap_or(Path::new(""));
+    let read_dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let mut paths = Vec::new();
+
+    for entry in fs::read_dir(read_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        if entry.file_type()?.is_file() && matches(&name, &file_name) {
+            paths.push(dir.join(file_name));
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+impl Composed {
+    fn rebuild(&mut self) {
+        let mut merged = Document::new();
+        let mut origins: Vec<(String, String, usize)> = Vec::new();
+
+        if let Some(version) = self.files.last().and_then(|(_, doc)| doc.version()) {
+            merged.set_version(version);
+        }
+
+        for (i, (_, doc)) in self.files.iter().enumerate() {
+            for section in doc.sections() {
+                if !merged.sections().iter().any(|s| s.name() == section.name()) {
+                    merged.push_section(Section::new(section.name()));
+                }
+
+                for (var, val) in section.entries() {
+                    if doc.is_marked(section.name(), var) {
+                        merged.put(section.name(), var, &format!("{} {}", secret::MARKER, val));
+                    } else {
+                        merged.put(section.name(), var, val);
+                    }
+
+                    match origins.iter_mut().find(|(k, v, _)| k == section.name() && v == var) {
+                        Some(origin) => origin.2 = i,
+                        None => origins.push((section.name().to_string(), var.clone(), i)),
+                    }
+                }
+            }
+        }
+
+        self.merged = merged;
+        self.origins = origins;
+    }
+
+    fn origin_index(&self, key: &str, var: &str) -> Option<usize> {
+        self.origins.iter().find(|(k, v, _)| k == key.trim() && v == var.trim()).map(|(_, _, i)| *i)
+    }
+
+    /// Собранный документ.
+    ///
+    /// The assembled document.
+    pub fn document(&self) -> &Document {
+        &self.merged
+    }
+
+    /// Все прочитанные файлы в порядке чтения; основной файл последний.
+    ///
+    /// All files read, in reading order; the main file is last.
+    pub fn files(&self) -> Vec<&Path> {
+        self.files.iter().map(|(path, _)| path.as_path()).collect()
+    }
+
+    /// Получает значение переменной из собранного документа.
+    ///
+    /// Gets the value of a variable from the assembled document.
+    pub fn get(&self, key: &str, var: &str) -> Option<&str> {
+        self.merged.get(key, var)
+    }
+
+    /// Возвращает файл, определяющий итоговое значение переменной.
+    ///
+    /// Returns the file that defines the effective value of the variable.
+    pub fn origin(&self, key: &str, var: &str) -> Option<&Path> {
+        self.origin_index(key, var).map(|i| self.files[i].0.as_path())
+    }
+
+    /// Устанавливает значение переменной и сохраняет файл, который её определяет.
+    /// Новые переменные записываются в основной файл.
+    ///
+    /// Sets the value of a variable and saves the file that defines it.
+    /// New variables are written to the main file.
+    pub fn set<T: std::fmt::Display, V: std::fmt::Display>(&mut self, key: &str, var: T, val: V) -> Result<(), Error> {
+        let var_str = var.to_string();
+        let i = self.origin_index(key, &var_str).unwrap_or(self.files.len() - 1);
+        let (path, doc) = &mut self.files[i];
+
+        doc.set(key, &var_str, val)?;
+        TNT::open(&*path)?.duplicates(self.duplicates).with_auditor(self.audit.clone()).save_as("set", doc)?;
+        self.rebuild();
+
+        Ok(())
+    }
+
+    /// Удаляет переменную из файла, который её определяет.
+    /// Если переменная определена и в более раннем файле, станет видно то значение.
+    ///
+    /// Deletes a variable from the file that defines it.
+    /// If an earlier file defines it too, that value becomes visible.
+    pub fn delete_var(&mut self, key: &str, var: &str) -> Result<(), Error> {
+        let i = self
+            .origin_index(key, var)
+            .ok_or_else(|| Error::VarNotFound { key: key.trim().to_string(), var: var.trim().to_string() })?;
+        let (path, doc) = &mut self.files[i];
+
+        doc.delete_var(key, var);
+        TNT::open(&*path)?.duplicates(self.duplicates).with_auditor(self.audit.clone()).save_as("delete_var", doc)?;
+        self.rebuild();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use std::env;
+
+use crate::document::Document;
+use crate::error::Error;
+use crate::secret;
+
+/// Стек разрешаемых переменных и признак того, что в значение попал секрет.
+#[derive(Default)]
+struct Resolution {
+    stack: Vec<String>,
+    is_secret: bool,
+}
+
+fn expand(doc: &Document, raw: &str, res: &mut Resolution) -> Result<String, Error> {
+    let mut out = String::new();
+    let mut rest = raw;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        if let Some(after) = rest.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+            continue;
+        }
+
+        let Some(after) = rest.strip_prefix("${") else {
+            out.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let end = after.find('}').ok_or_else(|| Error::Unresolved(rest.to_string()))?;
+        let reference = &after[..end];
+        rest = &after[end + 1..];
+
+        if let Some(name) = reference.strip_prefix("env:") {
+            let val = env::var(name.trim()).map_err(|_| Error::Unresolved(format!("${{{}}}", reference)))?;
+            res.is_secret |= secret::is_secret_name(name);
+            out.push_str(&val);
+            continue;
+        }
+
+        let (key, var) = reference.split_once('.').ok_or_else(|| Error::Unresolved(format!("${{{}}}", reference)))?;
+        let val = lookup(doc, key, var, res)?.ok_or_else(|| Error::Unresolved(format!("${{{}}}", reference)))?;
+        res.is_secret |= doc.is_secret(key, var);
+        out.push_str(&val);
+    }
+
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+fn lookup(doc: &Document, key: &str, var: &str, res: &mut Resolution) -> Result<Option<String>, Error> {
+    let name = format!("{}.{}", key.trim(), var.trim());
+
+    if let Some(pos) = res.stack.iter().position(|n| *n == name) {
+        let mut cycle = res.stack[pos..].to_vec();
+        cycle.push(name);
+        return Err(Error::Cycle(cycle));
+    }
+
+    let Some(raw) = doc.get(key, var) else {
+        return Ok(None);
+    };
+
+    res.stack.push(name);
+    let val = expand(doc, raw, res);
+    res.stack.pop();
+
+    val.map(Some)
+}
+
+impl Document {
+    /// Получает значение переменной с подстановкой ссылок `${section.var}` и `${env:NAME}`.
+    /// `$${` записывает буквальное `${`. Исходное значение доступно через `get`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Gets the value of a variable with `${section.var}` and `${env:NAME}` references substituted.
+    /// `$${` produces a literal `${`. The raw value is available through `get`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    ///
+    /// # Errors
+    /// `Error::Cycle` if references form a cycle, `Error::Unresolved` if a reference cannot be resolved.
+    pub fn resolve(&self, key: &str, var: &str) -> Result<Option<String>, Error> {
+        lookup(self, key, var, &mut Resolution::default())
+    }
+
+    /// Возвращает копию документа, в которой все значения разрешены (см. `resolve`).
+    /// Переменная, в значение которой подставлен секрет, сама помечается секретной,
+    /// поэтому экспорт без раскрытия скрывает и её.
+    ///
+    /// Returns a copy of the document with every value resolved (see `resolve`).
+    /// A variable whose value has a secret substituted into it is itself marked secret,
+    /// so an export without revealing hides it too.
+    pub fn resolved(&self) -> Result<Document, Error> {
+        let mut doc = self.clone();
+
+        for section in self.sections() {
+            for (var, _) in section.entries() {
+                let mut res = Resolution::default();
+
+                if let Some(val) = lookup(self, section.name(), var, &mut res)? {
+                    if res.is_secret {
+                        doc.put(section.name(), var, &format!("{} {}", secret::MARKER, val));
+                    } else {
+                        doc.put(section.name(), var, &val);
+                    }
+                }
+            }
+        }
+
+        Ok(doc)
+    }
+}
+
+pub(crate) fn resolve_value(doc: &Document, key: &str, var: &str, raw: &str) -> Result<String, Error> {
+    let mut res = Resolution { stack: vec![format!("{}.{}", key.trim(), var.trim())], is_secret: false };
+
+    expand(doc, raw, &mut res)
+}
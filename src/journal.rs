@@ -0,0 +1,443 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::document::Document;
+use crate::error::Error;
+use crate::patch::{self, Op, Patch};
+use crate::storage::{FileStorage, Storage, WritableStorage};
+use crate::tnt::TNT;
+
+const HEADER: &str = "@base ";
+const FOLDED: &str = "@folded ";
+
+/// Хранилище в режиме журнала. Изменения не переписывают основной файл, а дописываются
+/// в `{filename}.journal` записями операций; чтение накладывает журнал на основной файл,
+/// а `compact` переносит журнал в основной файл одной атомарной записью.
+///
+/// Журнал начинается с хеша основного файла, к которому он относится. Перед переносом в основной файл
+/// в журнал дописывается хеш нового текста, поэтому журнал, оставшийся от прерванного `compact`, игнорируется;
+/// журнал от другой версии файла (например, файл изменили вручную) — ошибка `InvalidData`, а не потеря записей.
+/// Каждая запись содержит контрольную сумму: оборванная последняя запись пропускается при чтении
+/// и отрезается при следующей записи. Журнал создаётся с правами доступа основного файла.
+/// Изменения, которые нельзя выразить операциями (директивы, перестановки), переписывают файл целиком.
+///
+/// Наложенный журнал кешируется до изменения любого из двух файлов, поэтому запись не перечитывает
+/// всю историю. С `compact_after` журнал переносится в основной файл автоматически.
+///
+/// Journal-mode storage. Changes do not rewrite the main file but are appended
+/// to `{filename}.journal` as operation records; a read replays the journal over the main file,
+/// and `compact` folds the journal into the main file in one atomic write.
+///
+/// The journal starts with the hash of the main file it belongs to. Before it is folded into the main file,
+/// the hash of the new text is appended, so a journal left over from an interrupted `compact` is ignored;
+/// a journal for another version of the file (say, the file was edited by hand) is an `InvalidData` error
+/// rather than lost records. Every record carries a checksum: a torn last record is skipped on read
+/// and cut off on the next write. The journal is created with the permissions of the main file.
+/// Changes that cannot be expressed as operations (directives, reorderings) rewrite the whole file.
+///
+/// The replayed journal is cached until either file changes, so a write does not re-read
+/// the whole history. With `compact_after` the journal is folded into the main file automatically.
+///
+/// # Example
+/// ```no_run
+/// # use tnt::OpenOptions;
+/// let tnt = OpenOptions::new().create().open_journaled("status.tnt").unwrap();
+///
+/// for i in 0..1000 {
+///     tnt.set("worker", "heartbeat", i).unwrap();
+/// }
+///
+/// tnt.compact().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct JournalStorage {
+    base: FileStorage,
+    journal: String,
+    compact_after: Option<usize>,
+    state: Cached,
+}
+
+/// Отметки основного файла и журнала, при которых действительно закешированное состояние.
+type Key = (Option<(SystemTime, u64)>, Option<(SystemTime, u64)>);
+
+/// Основной файл, разобранный журнал и текст после его наложения.
+struct State {
+    key: Key,
+    base: String,
+    journal: Journal,
+    text: String,
+}
+
+/// Кеш состояния, общий для клонов. `Debug` не выводит текст: в нём могут быть секреты.
+#[derive(Clone, Default)]
+struct Cached(Arc<Mutex<Option<Arc<State>>>>);
+
+impl fmt::Debug for Cached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cached").finish_non_exhaustive()
+    }
+}
+
+impl Cached {
+    fn get(&self) -> Option<Arc<State>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn set(&self, state: Option<Arc<State>>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = state;
+    }
+}
+
+#[derive(Clone)]
+struct Journal {
+    records: Vec<Patch>,
+    valid_len: u64,
+    len: u64,
+    is_current: bool,
+}
+
+impl JournalStorage {
+    /// Создаёт хранилище для основного файла и его журнала. Файлы при этом не создаются.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к основному файлу
+    ///
+    /// Creates storage for the main file and its journal. The files themselves are not created.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the main file
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let base = FileStorage::new(path);
+        let journal = format!("{}.journal", base.filename());
+
+        Self { base, journal, compact_after: None, state: Cached::default() }
+    }
+
+    /// Переносит журнал в основной файл автоматически, как только в нём набирается `records` записей.
+    ///
+    /// # Аргументы
+    /// * `records` - Число записей, после которого выполняется `compact`
+    ///
+    /// Folds the journal into the main file automatically as soon as it holds `records` records.
+    ///
+    /// # Arguments
+    /// * `records` - Number of records after which `compact` runs
+    pub fn compact_after(mut self, records: usize) -> Self {
+        self.compact_after = Some(records);
+        self
+    }
+
+    /// Имя основного файла.
+    ///
+    /// The name of the main file.
+    pub fn filename(&self) -> &str {
+        self.base.filename()
+    }
+
+    /// Имя файла журнала.
+    ///
+    /// The name of the journal file.
+    pub fn journal_filename(&self) -> &str {
+        &self.journal
+    }
+
+    /// Число записей в журнале, ещё не перенесённых в основной файл.
+    ///
+    /// The number of journal records not yet folded into the main file.
+    pub fn pending(&self) -> io::Result<usize> {
+        Ok(self.state()?.journal.records.len())
+    }
+
+    /// Переносит журнал в основной файл атомарной записью и удаляет журнал.
+    ///
+    /// Folds the journal into the main file with an atomic write and removes the journal.
+    pub fn compact(&self) -> io::Result<()> {
+        self.lock()?;
+
+        let result = self.state().and_then(|state| self.rewrite(&state.journal, &state.text));
+        self.unlock()?;
+
+        result
+    }
+
+    fn key(&self) -> io::Result<Key> {
+        let journal = match fs::metadata(&self.journal) {
+            Ok(meta) => meta.modified().ok().map(|modified| (modified, meta.len())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+
+        Ok((self.base.stamp()?, journal))
+    }
+
+    /// Текущее состояние: из кеша, если файлы не менялись, иначе заново прочитанное и наложенное.
+    fn state(&self) -> io::Result<Arc<State>> {
+        let key = self.key()?;
+
+        if let Some(state) = self.state.get()
+            && key.0.is_some()
+            && state.key == key
+        {
+            return Ok(state);
+        }
+
+        let base = self.base.read()?;
+        let journal = self.journal(&base)?;
+        let text = match journal.records.is_empty() {
+            true => base.clone(),
+            false => Self::replay(&base, &journal.records)?.render(),
+        };
+
+        let state = Arc::new(State { key, base, journal, text });
+        self.state.set(Some(Arc::clone(&state)));
+
+        Ok(state)
+    }
+
+    fn journal(&self, base: &str) -> io::Result<Journal> {
+        let text = match fs::read_to_string(&self.journal) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut journal = Journal { records: Vec::new(), valid_len: 0, len: text.len() as u64, is_current: false };
+        let mut lines = text.split_inclusive('\n');
+
+        let Some(header) = lines.next().and_then(|line| line.strip_suffix('\n')) else {
+            return Ok(journal);
+        };
+
+        if header.strip_prefix(HEADER) != Some(&format!("{:016x}", checksum(base))) {
+            let folded = format!("{}{:016x}", FOLDED, checksum(base));
+            let mut rest = lines.map(str::trim_end);
+
+            // Left over from a fold into exactly this text, or holds no records: nothing to lose.
+            if rest.clone().all(str::is_empty) || rest.any(|line| line == folded) {
+                return Ok(journal);
+            }
+
+            let msg = format!("{}: the journal belongs to another version of {}", self.journal, self.filename());
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+
+        journal.is_current = true;
+        journal.valid_len = header.len() as u64 + 1;
+
+        let mut lines = lines.peekable();
+        let mut number = 1;
+
+        while let Some(line) = lines.next() {
+            number += 1;
+
+            match record(line) {
+                Some(patch) => {
+                    journal.records.push(patch);
+                    journal.valid_len += line.len() as u64;
+                }
+                None if lines.peek().is_none() => break,
+                None => {
+                    let msg = format!("{}: record {} is corrupt", self.journal, number);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+                }
+            }
+        }
+
+        Ok(journal)
+    }
+
+    fn replay(base: &str, records: &[Patch]) -> Result<Document, Error> {
+        let mut doc = Document::parse(base)?;
+
+        for patch in records {
+            patch.apply(&mut doc)?;
+        }
+
+        Ok(doc)
+    }
+
+    /// Дописывает запись и возвращает новую длину журнала.
+    fn append(&self, base: &str, journal: &Journal, patch: &Patch) -> io::Result<u64> {
+        let mut file = if journal.is_current {
+            let file = OpenOptions::new().write(true).open(&self.journal)?;
+
+            if journal.valid_len < journal.len {
+                file.set_len(journal.valid_len)?;
+            }
+
+            file
+        } else {
+            let mut file = OpenOptions::new().write(true).truncate(true).create(true).open(&self.journal)?;
+
+            if let Ok(meta) = fs::metadata(self.filename()) {
+                file.set_permissions(meta.permissions())?;
+            }
+
+            writeln!(file, "{}{:016x}", HEADER, checksum(base))?;
+
+            file
+        };
+
+        let ops: Vec<String> = patch.ops().iter().map(patch::op_to_json).collect();
+        let json = format!("[{}]", ops.join(", "));
+
+        file.seek(SeekFrom::End(0))?;
+        writeln!(file, "{:016x}\t{}", checksum(&json), json)?;
+        file.sync_data()?;
+
+        file.stream_position()
+    }
+
+    /// Переписывает основной файл и удаляет журнал. Текущий журнал сначала помечается хешем нового текста,
+    /// чтобы после сбоя между записью файла и удалением журнала его записи не наложились повторно.
+    fn rewrite(&self, journal: &Journal, text: &str) -> io::Result<()> {
+        self.state.set(None);
+
+        if journal.is_current {
+            let mut file = OpenOptions::new().write(true).open(&self.journal)?;
+            file.set_len(journal.valid_len)?;
+            file.seek(SeekFrom::End(0))?;
+            writeln!(file, "{}{:016x}", FOLDED, checksum(text))?;
+            file.sync_data()?;
+        } else if let Err(err) = fs::remove_file(&self.journal)
+            && err.kind() != io::ErrorKind::NotFound
+        {
+            return Err(err);
+        }
+
+        self.base.write(text)?;
+
+        match fs::remove_file(&self.journal) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Storage for JournalStorage {
+    fn read(&self) -> io::Result<String> {
+        Ok(self.state()?.text.clone())
+    }
+}
+
+impl WritableStorage for JournalStorage {
+    fn write(&self, text: &str) -> io::Result<()> {
+        let state = self.state()?;
+
+        let (Ok(current), Ok(target)) = (Document::parse(&state.text), Document::parse(text)) else {
+            return self.rewrite(&state.journal, text);
+        };
+
+        let patch = ops(&current, &target).into_iter().fold(Patch::new(), Patch::push);
+
+        if patch.ops().is_empty() && current.render() == target.render() {
+            return Ok(());
+        }
+
+        let mut check = current.clone();
+
+        if patch.apply(&mut check).is_err() || check.render() != target.render() {
+            return self.rewrite(&state.journal, text);
+        }
+
+        let len = self.append(&state.base, &state.journal, &patch)?;
+        let mut journal = state.journal.clone();
+        journal.records.push(patch);
+        journal.valid_len = len;
+        journal.len = len;
+        journal.is_current = true;
+
+        let text = check.render();
+
+        if self.compact_after.is_some_and(|limit| journal.records.len() >= limit) {
+            return self.rewrite(&journal, &text);
+        }
+
+        // Another writer may have appended in between; then the next read replays from disk.
+        let key = self.key()?;
+        let state = match key.1.is_some_and(|(_, journal_len)| journal_len == len) {
+            true => Some(Arc::new(State { key, base: state.base.clone(), journal, text })),
+            false => None,
+        };
+        self.state.set(state);
+
+        Ok(())
+    }
+
+    fn lock(&self) -> io::Result<()> {
+        self.base.lock()
+    }
+
+    fn unlock(&self) -> io::Result<()> {
+        self.base.unlock()
+    }
+}
+
+impl TNT<JournalStorage> {
+    /// Переносит журнал в основной файл. См. `JournalStorage::compact`.
+    ///
+    /// Folds the journal into the main file. See `JournalStorage::compact`.
+    pub fn compact(&self) -> Result<(), Error> {
+        self.storage().compact()?;
+
+        Ok(())
+    }
+}
+
+/// Операции, переводящие документ `a` в `b`: удаление секций и переменных, создание пустых секций и установка значений.
+fn ops(a: &Document, b: &Document) -> Vec<Op> {
+    let mut ops = Vec::new();
+
+    for section in a.sections() {
+        if b.section(section.name()).is_none() {
+            ops.push(Op::DeleteSection { key: section.name().to_string() });
+        }
+    }
+
+    for section in b.sections() {
+        let key = section.name();
+        let old = a.section(key);
+
+        if old.is_none() && section.entries().is_empty() {
+            ops.push(Op::AddSection { key: key.to_string() });
+        }
+
+        if let Some(old) = old {
+            for (var, _) in old.entries() {
+                if section.get(var).is_none() {
+                    ops.push(Op::Delete { key: key.to_string(), var: var.clone() });
+                }
+            }
+        }
+
+        for (var, val) in section.entries() {
+            let is_same = old.is_some_and(|old| old.get(var) == Some(val) && a.is_marked(key, var) == b.is_marked(key, var));
+
+            if !is_same {
+                ops.push(Op::Set { key: key.to_string(), var: var.clone(), val: patch::stored(b, key, var, val) });
+            }
+        }
+    }
+
+    ops
+}
+
+/// Разбирает запись журнала `{checksum}\t{json}\n`. `None` — запись оборвана или повреждена.
+fn record(line: &str) -> Option<Patch> {
+    let (sum, json) = line.strip_suffix('\n')?.split_once('\t')?;
+
+    if sum != format!("{:016x}", checksum(json)) {
+        return None;
+    }
+
+    Patch::from_json(json).ok()
+}
+
+/// FNV-1a: стабильная между запусками и версиями компилятора сумма для записи на диск.
+fn checksum(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
@@ -1 +1,43 @@
-pub mod tnt;
\ No newline at end of file
+#[cfg(feature = "async")]
+pub mod async_tnt;
+pub mod audit;
+pub mod diff;
+pub mod document;
+pub mod error;
+pub mod history;
+pub mod include;
+mod interpolate;
+pub mod journal;
+mod json;
+mod list;
+pub mod merge;
+pub mod migrate;
+pub mod open;
+pub mod patch;
+pub mod profile;
+pub mod secret;
+pub mod shared;
+pub mod storage;
+pub mod tnt;
+pub mod watch;
+
+#[cfg(feature = "async")]
+pub use async_tnt::AsyncTnt;
+pub use audit::{AuditRecord, AuditSink, JsonLinesSink};
+pub use diff::{Change, Diff, diff, diff_with_secrets};
+pub use document::{Document, DuplicatePolicy, Entry, Section};
+pub use error::{Error, Result};
+pub use history::{HistoryStorage, Version};
+pub use include::Composed;
+pub use journal::JournalStorage;
+pub use merge::{Conflict, Merge, merge};
+pub use migrate::{MigrationReport, Migrator};
+pub use open::OpenOptions;
+pub use patch::{Op, Patch};
+pub use profile::Profile;
+#[cfg(feature = "encryption")]
+pub use secret::SecretKey;
+pub use shared::{SharedConfig, Snapshot};
+pub use storage::{FileStorage, MemoryStorage, ReadOnly, Storage, WritableStorage};
+pub use tnt::TNT;
+pub use watch::{WatchHandle, Watcher};
@@ -0,0 +1,303 @@
+use crate::document::Document;
+use crate::error::Error;
+use crate::json;
+use crate::storage::{Storage, WritableStorage};
+use crate::tnt::TNT;
+
+/// Возвращает `true`, если значение записано как список `[a, b, c]`.
+pub(crate) fn is_list(val: &str) -> bool {
+    let val = val.trim();
+
+    val.starts_with('[') && val.ends_with(']')
+}
+
+/// Разбирает значение в список. Обычное значение — список из одного элемента, пустое — пустой список.
+/// Элементы с запятыми, скобками или крайними пробелами записываются в кавычках: `["a, b", c]`.
+pub(crate) fn parse(val: &str) -> Vec<String> {
+    let val = val.trim();
+
+    let Some(inner) = val.strip_prefix('[').and_then(|v| v.strip_suffix(']')) else {
+        return if val.is_empty() { Vec::new() } else { vec![val.to_string()] };
+    };
+
+    if inner.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut is_quoted = false;
+    let mut was_quoted = false;
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if is_quoted => item.extend(chars.next()),
+            '"' => {
+                if !is_quoted && item.trim().is_empty() {
+                    item.clear();
+                }
+
+                is_quoted = !is_quoted;
+                was_quoted = true;
+            }
+            ',' if !is_quoted => {
+                items.push(if was_quoted { item.clone() } else { item.trim().to_string() });
+                item.clear();
+                was_quoted = false;
+            }
+            c if was_quoted && !is_quoted && c.is_whitespace() => {}
+            c => item.push(c),
+        }
+    }
+
+    items.push(if was_quoted { item } else { item.trim().to_string() });
+
+    items
+}
+
+/// Записывает список в виде `[a, b, c]`.
+pub(crate) fn format<S: AsRef<str>>(items: &[S]) -> String {
+    let items: Vec<String> = items
+        .iter()
+        .map(|item| {
+            let item = item.as_ref();
+            let needs_quotes = item.is_empty()
+                || item.trim() != item
+                || item.contains([',', '[', ']', '"', '\\']);
+
+            if needs_quotes {
+                format!("\"{}\"", item.replace('\\', "\\\\").replace('"', "\\\""))
+            } else {
+                item.to_string()
+            }
+        })
+        .collect();
+
+    format!("[{}]", items.join(", "))
+}
+
+/// Записывает список как массив TOML/JSON: `["a", "b", "c"]`.
+pub(crate) fn to_array(val: &str) -> String {
+    let items: Vec<String> = parse(val).iter().map(|item| format!("\"{}\"", json::escape(item))).collect();
+
+    format!("[{}]", items.join(", "))
+}
+
+impl Document {
+    /// Получает значение переменной как список. Обычное значение возвращается списком из одного элемента.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Gets the value of a variable as a list. A plain value is returned as a one-item list.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    pub fn get_list(&self, key: &str, var: &str) -> Option<Vec<String>> {
+        self.get(key, var).map(parse)
+    }
+
+    /// Добавляет элемент в конец списка, создавая переменную при необходимости.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `item` - Новый элемент
+    ///
+    /// Appends an item to the end of the list, creating the variable as needed.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `item` - New item
+    ///
+    /// # Errors
+    /// `Error::Parse` if the item would break the markup, as in `Document::set`.
+    pub fn push<V: std::fmt::Display>(&mut self, key: &str, var: &str, item: V) -> Result<(), Error> {
+        let mut items = self.get_list(key, var).unwrap_or_default();
+        items.push(item.to_string());
+
+        self.set(key, var, format(&items))
+    }
+
+    /// Удаляет первое вхождение элемента из списка. Возвращает `true`, если элемент был найден.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `item` - Удаляемый элемент
+    ///
+    /// Removes the first occurrence of an item from the list. Returns `true` if the item was found.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `item` - Item to remove
+    pub fn remove_item(&mut self, key: &str, var: &str, item: &str) -> bool {
+        let mut items = self.get_list(key, var).unwrap_or_default();
+
+        let Some(pos) = items.iter().position(|i| i == item) else {
+            return false;
+        };
+
+        items.remove(pos);
+        self.put(key, var, &format(&items));
+
+        true
+    }
+
+    /// Вставляет элемент в список на указанную позицию, сдвигая последующие элементы.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `index` - Позиция (от 0 до длины списка включительно)
+    /// * `item` - Новый элемент
+    ///
+    /// Inserts an item into the list at the given position, shifting the following items.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `index` - Position (from 0 up to and including the list length)
+    /// * `item` - New item
+    ///
+    /// # Errors
+    /// `Error::IndexOutOfRange` if `index` is greater than the list length,
+    /// `Error::Parse` if the item would break the markup, as in `Document::set`.
+    pub fn insert_at<V: std::fmt::Display>(&mut self, key: &str, var: &str, index: usize, item: V) -> Result<(), Error> {
+        let mut items = self.get_list(key, var).unwrap_or_default();
+
+        if index > items.len() {
+            return Err(Error::IndexOutOfRange {
+                key: key.trim().to_string(),
+                var: var.trim().to_string(),
+                index,
+                len: items.len(),
+            });
+        }
+
+        items.insert(index, item.to_string());
+
+        self.set(key, var, format(&items))
+    }
+}
+
+impl<S: Storage> TNT<S> {
+    /// Получает значение переменной как список `[a, b, c]` с подстановкой ссылок `${...}`.
+    /// Обычное значение возвращается списком из одного элемента.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Gets the value of a variable as a `[a, b, c]` list with `${...}` references substituted.
+    /// A plain value is returned as a one-item list.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use tnt::TNT;
+    /// let tnt = TNT::open("data.txt").unwrap();
+    /// tnt.push("cluster", "hosts", "db3").unwrap();
+    ///
+    /// let hosts = tnt.get_list("cluster", "hosts").unwrap();
+    /// ```
+    pub fn get_list(&self, key: &str, var: &str) -> Result<Vec<String>, Error> {
+        let val = self
+            .load()?
+            .resolve(key, var)?
+            .ok_or_else(|| Error::VarNotFound { key: key.trim().to_string(), var: var.trim().to_string() })?;
+
+        Ok(parse(&val))
+    }
+}
+
+impl<S: WritableStorage> TNT<S> {
+    /// Добавляет элемент в конец списка, создавая переменную при необходимости.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `item` - Новый элемент
+    ///
+    /// Appends an item to the end of the list, creating the variable as needed.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `item` - New item
+    pub fn push<V: std::fmt::Display>(&self, key: &str, var: &str, item: V) -> Result<(), Error> {
+        self.locked(|| {
+            let mut doc = self.load()?;
+            doc.push(key, var, item)?;
+
+            self.write_list(&doc, key, var)
+        })
+    }
+
+    /// Удаляет первое вхождение элемента из списка. Возвращает `true`, если элемент был найден.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `item` - Удаляемый элемент
+    ///
+    /// Removes the first occurrence of an item from the list. Returns `true` if the item was found.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `item` - Item to remove
+    pub fn remove_item(&self, key: &str, var: &str, item: &str) -> Result<bool, Error> {
+        self.locked(|| {
+            let mut doc = self.load()?;
+
+            if !doc.remove_item(key, var, item) {
+                return Ok(false);
+            }
+
+            self.write_list(&doc, key, var)?;
+
+            Ok(true)
+        })
+    }
+
+    /// Вставляет элемент в список на указанную позицию. См. `Document::insert_at`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `index` - Позиция (от 0 до длины списка включительно)
+    /// * `item` - Новый элемент
+    ///
+    /// Inserts an item into the list at the given position. See `Document::insert_at`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `index` - Position (from 0 up to and including the list length)
+    /// * `item` - New item
+    pub fn insert_at<V: std::fmt::Display>(&self, key: &str, var: &str, index: usize, item: V) -> Result<(), Error> {
+        self.locked(|| {
+            let mut doc = self.load()?;
+            doc.insert_at(key, var, index, item)?;
+
+            self.write_list(&doc, key, var)
+        })
+    }
+
+    fn write_list(&self, doc: &Document, key: &str, var: &str) -> Result<(), Error> {
+        if let Some(val) = doc.get(key, var) {
+            self.upsert(&[(key, var, val)])?;
+        }
+
+        Ok(())
+    }
+}
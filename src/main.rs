@@ -0,0 +1,148 @@
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+use std::time::UNIX_EPOCH;
+
+use tnt::{Document, Error, HistoryStorage, TNT};
+
+const USAGE: &str = "Usage:
+    tnt diff [--json] <a.tnt> <b.tnt>
+    tnt merge [--driver] <base.tnt> <ours.tnt> <theirs.tnt>
+    tnt history <file.tnt>
+    tnt show <file.tnt> <version>
+    tnt rollback <file.tnt> <version>
+
+As a git merge driver (.git/config and .gitattributes):
+    [merge \"tnt\"]
+        driver = tnt merge --driver %O %A %B
+    *.tnt merge=tnt";
+
+fn open(path: &str) -> Result<Document, Error> {
+    let doc = TNT::open_read_only(path)?.load()?;
+
+    for section in doc.shadowed() {
+        if section.entries().is_empty() {
+            eprintln!("warning: {}: repeated section `{}` is ignored", path, section.name());
+        }
+
+        for (var, _) in section.entries() {
+            eprintln!("warning: {}: repeated `{}.{}` is ignored", path, section.name(), var);
+        }
+    }
+
+    Ok(doc)
+}
+
+fn diff(args: &[String]) -> Result<ExitCode, Error> {
+    let json = args.iter().any(|a| a == "--json");
+    let files: Vec<&String> = args.iter().filter(|a| *a != "--json").collect();
+
+    if files.len() != 2 {
+        eprintln!("{}", USAGE);
+        return Ok(ExitCode::from(2));
+    }
+
+    let changes = tnt::diff(&open(files[0])?, &open(files[1])?);
+
+    if json {
+        println!("{}", changes.to_json());
+    } else {
+        print!("{}", changes);
+    }
+
+    Ok(if changes.is_empty() { ExitCode::SUCCESS } else { ExitCode::from(1) })
+}
+
+fn merge(args: &[String]) -> Result<ExitCode, Error> {
+    let driver = args.iter().any(|a| a == "--driver");
+    let files: Vec<&String> = args.iter().filter(|a| *a != "--driver").collect();
+
+    if files.len() != 3 {
+        eprintln!("{}", USAGE);
+        return Ok(ExitCode::from(2));
+    }
+
+    let merged = tnt::merge(&open(files[0])?, &open(files[1])?, &open(files[2])?);
+
+    if driver {
+        fs::write(files[1], merged.render())?;
+    } else {
+        print!("{}", merged.render());
+    }
+
+    for conflict in merged.conflicts() {
+        eprintln!("conflict: section `{}`: `{}`", conflict.key, conflict.var);
+    }
+
+    Ok(if merged.is_clean() { ExitCode::SUCCESS } else { ExitCode::from(1) })
+}
+
+fn with_history(path: &str) -> Result<TNT<HistoryStorage>, Error> {
+    TNT::open(path)?;
+
+    Ok(TNT::with_storage(HistoryStorage::new(path)))
+}
+
+fn version(arg: &str) -> Result<u64, Error> {
+    arg.parse().map_err(|_| Error::Parse(format!("'{}' is not a version number", arg)))
+}
+
+fn history(args: &[String]) -> Result<ExitCode, Error> {
+    let [file] = args else {
+        eprintln!("{}", USAGE);
+        return Ok(ExitCode::from(2));
+    };
+
+    for version in with_history(file)?.history()? {
+        let secs = version.time().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        println!("{}\t{}\t{}", version.number(), secs, version.summary());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn show(args: &[String]) -> Result<ExitCode, Error> {
+    let [file, number] = args else {
+        eprintln!("{}", USAGE);
+        return Ok(ExitCode::from(2));
+    };
+
+    print!("{}", with_history(file)?.show(version(number)?)?);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn rollback(args: &[String]) -> Result<ExitCode, Error> {
+    let [file, number] = args else {
+        eprintln!("{}", USAGE);
+        return Ok(ExitCode::from(2));
+    };
+
+    with_history(file)?.rollback(version(number)?)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("diff") => diff(&args[1..]),
+        Some("merge") => merge(&args[1..]),
+        Some("history") => history(&args[1..]),
+        Some("show") => show(&args[1..]),
+        Some("rollback") => rollback(&args[1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("tnt: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use crate::document::{Document, Section};
+
+/// Конфликт слияния: переменная изменена по-разному в обеих ветках.
+/// `None` означает, что в соответствующей версии переменной нет.
+///
+/// A merge conflict: the variable was changed differently on both sides.
+/// `None` means the variable is absent in that version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub key: String,
+    pub var: String,
+    pub base: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+/// Результат трёхстороннего слияния: объединённый документ и список конфликтов.
+/// Для конфликтующих переменных в документе оставлено значение из `ours`
+/// (или из `theirs`, если в `ours` переменная удалена).
+///
+/// The outcome of a three-way merge: the merged document and the list of conflicts.
+/// Conflicting variables keep the `ours` value in the document
+/// (or the `theirs` value if `ours` deleted the variable).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merge {
+    doc: Document,
+    conflicts: Vec<Conflict>,
+}
+
+/// Выполняет трёхстороннее слияние на уровне секций и переменных.
+/// Удаление секции в одной ветке и изменение её переменных в другой считается конфликтом.
+/// Директивы `@include` сливаются так же: путь, удалённый в одной из веток, удаляется.
+///
+/// # Аргументы
+/// * `base` - Общий предок
+/// * `ours` - Наша версия
+/// * `theirs` - Их версия
+///
+/// Performs a three-way merge at the section and variable level.
+/// Deleting a section on one side while changing its variables on the other is a conflict.
+/// `@include` directives are merged the same way: a path removed on either side is removed.
+///
+/// # Arguments
+/// * `base` - Common ancestor
+/// * `ours` - Our version
+/// * `theirs` - Their version
+pub fn merge(base: &Document, ours: &Document, theirs: &Document) -> Merge {
+    let mut doc = Document::new();
+    let mut conflicts = Vec::new();
+
+    if let Some(version) = ours.version().max(theirs.version()) {
+        doc.set_version(version);
+    }
+
+    // An include stays unless one side removed it; a new one from either side is added.
+    for path in ours.includes().iter().chain(theirs.includes()) {
+        let in_base = base.includes().contains(path);
+
+        if !in_base || (ours.includes().contains(path) && theirs.includes().contains(path)) {
+            doc.add_include(path);
+        }
+    }
+
+    let mut keys: Vec<&str> = Vec::new();
+
+    for section in ours.sections().iter().chain(theirs.sections()) {
+        if !keys.contains(&section.name()) {
+            keys.push(section.name());
+        }
+    }
+
+    for key in keys {
+        let in_base = has_section(base, key);
+        let in_ours = has_section(ours, key);
+        let in_theirs = has_section(theirs, key);
+
+        // A section deleted on one side conflicts with any change to it on the other side.
+        let dropped_by_ours = in_base && !in_ours;
+        let dropped_by_theirs = in_base && !in_theirs;
+
+        let mut vars: Vec<&str> = Vec::new();
+
+        for (var, _) in ours.entries(key).iter().chain(theirs.entries(key)) {
+            if !vars.contains(&var.as_str()) {
+                vars.push(var);
+            }
+        }
+
+        let mut section = Section::new(key);
+
+        for var in vars {
+            let b = base.get(key, var);
+            let o = ours.get(key, var);
+            let t = theirs.get(key, var);
+
+            let is_conflict = (dropped_by_ours && t != b) || (dropped_by_theirs && o != b) || (o != b && t != b);
+
+            let merged = if o == t {
+                o
+            } else if !is_conflict && o == b {
+                t
+            } else if !is_conflict {
+                o
+            } else {
+                conflicts.push(Conflict {
+                    key: key.to_string(),
+                    var: var.to_string(),
+                    base: b.map(str::to_string),
+                    ours: o.map(str::to_string),
+                    theirs: t.map(str::to_string),
+                });
+                o.or(t)
+            };
+
+            if let Some(val) = merged {
+                section.push(var, val);
+
+                if ours.is_marked(key, var) || theirs.is_marked(key, var) {
+                    section.mark_secret(var);
+                }
+            }
+        }
+
+        // A section deleted on one side survives only if something in it is left.
+        let is_deleted = in_base && !(in_ours && in_theirs);
+
+        if !is_deleted || !section.entries().is_empty() {
+            doc.push_section(section);
+        }
+    }
+
+    Merge { doc, conflicts }
+}
+
+fn has_section(doc: &Document, key: &str) -> bool {
+    doc.sections().iter().any(|s| s.name() == key)
+}
+
+impl Merge {
+    /// Объединённый документ.
+    ///
+    /// The merged document.
+    pub fn document(&self) -> &Document {
+        &self.doc
+    }
+
+    /// Список конфликтов.
+    ///
+    /// The list of conflicts.
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Возвращает `true`, если слияние прошло без конфликтов.
+    ///
+    /// Returns `true` if the merge has no conflicts.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Выводит объединённый документ в формате TNT,
+    /// заключая конфликтующие переменные в маркеры `<<<<<<<` / `=======` / `>>>>>>>`.
+    ///
+    /// Renders the merged document in TNT format,
+    /// wrapping conflicting variables in `<<<<<<<` / `=======` / `>>>>>>>` markers.
+    pub fn render(&self) -> String {
+        let mut txt = self.doc.render_directives();
+
+        for section in self.doc.sections() {
+            txt.push_str(&format!("({}) {{\n", section.name()));
+
+            for (var, val) in section.entries() {
+                let conflict = self.conflicts.iter().find(|c| c.key == section.name() && c.var == *var);
+
+                match conflict {
+                    Some(c) => {
+                        txt.push_str("<<<<<<< ours\n");
+
+                        if let Some(ours) = &c.ours {
+                            txt.push_str(&section.render_entry(var, ours));
+                        }
+
+                        txt.push_str("=======\n");
+
+                        if let Some(theirs) = &c.theirs {
+                            txt.push_str(&section.render_entry(var, theirs));
+                        }
+
+                        txt.push_str(">>>>>>> theirs\n");
+                    }
+                    None => txt.push_str(&section.render_entry(var, val)),
+                }
+            }
+
+            txt.push_str("}\n");
+        }
+
+        txt
+    }
+}
@@ -0,0 +1,162 @@
+use crate::diff::{self, Diff};
+use crate::document::Document;
+use crate::error::Error;
+use crate::storage::{Storage, WritableStorage};
+use crate::tnt::TNT;
+
+type Step = Box<dyn Fn(&mut Document) -> Result<(), Error>>;
+
+/// Набор упорядоченных шагов миграции TNT-документа.
+/// Каждый шаг имеет номер версии; применяются только шаги с номером больше `@version` документа.
+///
+/// A set of ordered migration steps for a TNT document.
+/// Each step has a version number; only steps above the document's `@version` are applied.
+///
+/// # Example
+/// ```no_run
+/// # use tnt::{Migrator, TNT};
+/// let tnt = TNT::open("app.tnt").unwrap();
+/// let migrator = Migrator::new()
+///     .step(1, "rename db section", |doc| doc.rename_section("db", "database"))
+///     .step(2, "default pool size", |doc| doc.set("database", "pool", 10));
+///
+/// let report = migrator.migrate(&tnt).unwrap();
+/// println!("{}", report.diff());
+/// ```
+#[derive(Default)]
+pub struct Migrator {
+    steps: Vec<(u32, String, Step)>,
+}
+
+/// Результат миграции: применённые шаги и документ до и после.
+///
+/// The outcome of a migration: the applied steps and the document before and after.
+#[derive(Debug, Clone)]
+pub struct MigrationReport {
+    applied: Vec<(u32, String)>,
+    before: Document,
+    after: Document,
+}
+
+impl Migrator {
+    /// Создаёт пустой набор миграций.
+    ///
+    /// Creates an empty set of migrations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Регистрирует шаг миграции. Версии должны строго возрастать.
+    ///
+    /// # Аргументы
+    /// * `version` - Версия документа после применения шага
+    /// * `description` - Описание шага
+    /// * `step` - Функция, изменяющая документ
+    ///
+    /// Registers a migration step. Versions must be strictly increasing.
+    ///
+    /// # Arguments
+    /// * `version` - Document version after the step is applied
+    /// * `description` - Step description
+    /// * `step` - Function that changes the document
+    ///
+    /// # Panics
+    /// Panics if `version` is not greater than the previously registered one.
+    pub fn step<F>(mut self, version: u32, description: &str, step: F) -> Self
+    where
+        F: Fn(&mut Document) -> Result<(), Error> + 'static,
+    {
+        if let Some((last, _, _)) = self.steps.last() {
+            assert!(version > *last, "Migration versions must be strictly increasing!");
+        }
+
+        self.steps.push((version, description.to_string(), Box::new(step)));
+        self
+    }
+
+    /// Возвращает версии шагов, которые ещё не применены к документу.
+    ///
+    /// Returns the versions of the steps not yet applied to the document.
+    pub fn pending(&self, doc: &Document) -> Vec<u32> {
+        let current = doc.version().unwrap_or(0);
+
+        self.steps.iter().map(|(v, _, _)| *v).filter(|v| *v > current).collect()
+    }
+
+    /// Применяет ожидающие шаги к документу в памяти.
+    /// Если какой-либо шаг завершился ошибкой, документ не изменяется.
+    ///
+    /// Applies the pending steps to an in-memory document.
+    /// If any step fails, the document is left unchanged.
+    pub fn apply(&self, doc: &mut Document) -> Result<Vec<(u32, String)>, Error> {
+        let current = doc.version().unwrap_or(0);
+        let mut work = doc.clone();
+        let mut applied = Vec::new();
+
+        for (version, description, step) in self.steps.iter().filter(|(v, _, _)| *v > current) {
+            step(&mut work)?;
+            work.set_version(*version);
+            applied.push((*version, description.clone()));
+        }
+
+        *doc = work;
+
+        Ok(applied)
+    }
+
+    /// Применяет ожидающие шаги к файлу и сохраняет его одной атомарной записью.
+    /// Повторный вызов ничего не меняет.
+    ///
+    /// Applies the pending steps to the file and saves it in one atomic write.
+    /// Calling it again changes nothing.
+    pub fn migrate<S: WritableStorage>(&self, tnt: &TNT<S>) -> Result<MigrationReport, Error> {
+        let report = self.dry_run(tnt)?;
+
+        if !report.applied.is_empty() {
+            tnt.save_as("migrate", &report.after)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Вычисляет результат миграции, не изменяя файл.
+    ///
+    /// Computes the outcome of a migration without changing the file.
+    pub fn dry_run<S: Storage>(&self, tnt: &TNT<S>) -> Result<MigrationReport, Error> {
+        let before = tnt.load()?;
+        let mut after = before.clone();
+        let applied = self.apply(&mut after)?;
+
+        Ok(MigrationReport { applied, before, after })
+    }
+}
+
+impl MigrationReport {
+    /// Применённые шаги: (версия, описание).
+    ///
+    /// The applied steps: (version, description).
+    pub fn applied(&self) -> &[(u32, String)] {
+        &self.applied
+    }
+
+    /// Документ до миграции.
+    ///
+    /// The document before the migration.
+    pub fn before(&self) -> &Document {
+        &self.before
+    }
+
+    /// Документ после миграции.
+    ///
+    /// The document after the migration.
+    pub fn after(&self) -> &Document {
+        &self.after
+    }
+
+    /// Смысловые изменения между документом до и после миграции.
+    ///
+    /// The semantic changes between the document before and after the migration.
+    pub fn diff(&self) -> Diff {
+        diff::diff(&self.before, &self.after)
+    }
+}
@@ -0,0 +1,153 @@
+use std::fs::{self, OpenOptions as FsOpenOptions};
+use std::io;
+use std::path::Path;
+
+use crate::document::DuplicatePolicy;
+use crate::error::Error;
+use crate::journal::JournalStorage;
+use crate::storage::{FileStorage, ReadOnly};
+use crate::tnt::TNT;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    MustExist,
+    Create,
+    CreateNew,
+}
+
+/// Параметры открытия TNT-файла в духе `std::fs::OpenOptions`.
+/// По умолчанию файл должен существовать: опечатка в пути даёт ошибку, а не пустой конфиг.
+///
+/// Options for opening a TNT file in the spirit of `std::fs::OpenOptions`.
+/// By default the file must exist: a typo in the path is an error, not an empty config.
+///
+/// # Пример
+/// ```no_run
+/// # use tnt::OpenOptions;
+/// let tnt = OpenOptions::new().create().open("app.tnt").unwrap();
+/// let ro = OpenOptions::new().open_read_only("app.tnt").unwrap();
+/// ```
+///
+/// # Example
+/// ```no_run
+/// # use tnt::OpenOptions;
+/// let tnt = OpenOptions::new().create().open("app.tnt").unwrap();
+/// let ro = OpenOptions::new().open_read_only("app.tnt").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct OpenOptions {
+    mode: Mode,
+    duplicates: DuplicatePolicy,
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpenOptions {
+    /// Параметры по умолчанию: файл должен существовать.
+    ///
+    /// Default options: the file must exist.
+    pub fn new() -> Self {
+        Self { mode: Mode::MustExist, duplicates: DuplicatePolicy::default() }
+    }
+
+    /// Файл должен существовать, иначе `open` вернёт `Error::Io` с `NotFound`.
+    ///
+    /// The file must exist, otherwise `open` returns `Error::Io` with `NotFound`.
+    pub fn must_exist(mut self) -> Self {
+        self.mode = Mode::MustExist;
+        self
+    }
+
+    /// Создать пустой файл, если его нет.
+    ///
+    /// Create an empty file if it does not exist.
+    pub fn create(mut self) -> Self {
+        self.mode = Mode::Create;
+        self
+    }
+
+    /// Создать новый файл; если он уже есть, `open` вернёт `Error::Io` с `AlreadyExists`.
+    ///
+    /// Create a new file; if it already exists, `open` returns `Error::Io` with `AlreadyExists`.
+    pub fn create_new(mut self) -> Self {
+        self.mode = Mode::CreateNew;
+        self
+    }
+
+    /// Политика для повторяющихся секций и переменных (см. `TNT::duplicates`).
+    ///
+    /// # Аргументы
+    /// * `policy` - Политика дубликатов
+    ///
+    /// The policy for repeated sections and variables (see `TNT::duplicates`).
+    ///
+    /// # Arguments
+    /// * `policy` - Duplicate policy
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Открывает файл для чтения и записи.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к файлу
+    ///
+    /// Opens the file for reading and writing.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<TNT, Error> {
+        let storage = self.prepare(path.as_ref())?;
+
+        Ok(TNT::with_storage(storage).duplicates(self.duplicates))
+    }
+
+    /// Открывает файл только для чтения. У полученного экземпляра нет методов записи.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к файлу
+    ///
+    /// Opens the file read-only. The returned instance has no write methods.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    pub fn open_read_only<P: AsRef<Path>>(&self, path: P) -> Result<TNT<ReadOnly<FileStorage>>, Error> {
+        let storage = self.prepare(path.as_ref())?;
+
+        Ok(TNT::with_storage(ReadOnly::new(storage)).duplicates(self.duplicates))
+    }
+
+    /// Открывает файл в режиме журнала (см. `JournalStorage`).
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к основному файлу
+    ///
+    /// Opens the file in journal mode (see `JournalStorage`).
+    ///
+    /// # Arguments
+    /// * `path` - Path to the main file
+    pub fn open_journaled<P: AsRef<Path>>(&self, path: P) -> Result<TNT<JournalStorage>, Error> {
+        self.prepare(path.as_ref())?;
+
+        Ok(TNT::with_storage(JournalStorage::new(path)).duplicates(self.duplicates))
+    }
+
+    fn prepare(&self, path: &Path) -> Result<FileStorage, Error> {
+        let checked = match self.mode {
+            Mode::MustExist => fs::metadata(path).and_then(|meta| {
+                if meta.is_file() { Ok(()) } else { Err(io::Error::new(io::ErrorKind::InvalidInput, "not a file")) }
+            }),
+            Mode::Create => FsOpenOptions::new().append(true).create(true).open(path).map(drop),
+            Mode::CreateNew => FsOpenOptions::new().write(true).create_new(true).open(path).map(drop),
+        };
+
+        checked.map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+
+        Ok(FileStorage::new(path))
+    }
+}
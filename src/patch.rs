@@ -0,0 +1,414 @@
+use std::fmt;
+
+use crate::document::{Document, Section};
+use crate::error::Error;
+use crate::json::{self, Value};
+use crate::secret;
+use crate::storage::WritableStorage;
+use crate::tnt::TNT;
+
+/// Одна операция патча. `Debug` скрывает значения: операция не знает, секретна ли переменная.
+///
+/// A single patch operation. `Debug` hides the values: an operation does not know whether a variable is secret.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Op {
+    /// Устанавливает значение переменной (см. `TNT::set`).
+    ///
+    /// Sets the value of a variable (see `TNT::set`).
+    Set { key: String, var: String, val: String },
+    /// Удаляет переменную.
+    ///
+    /// Deletes a variable.
+    Delete { key: String, var: String },
+    /// Создаёт пустую секцию.
+    ///
+    /// Creates an empty section.
+    AddSection { key: String },
+    /// Удаляет секцию.
+    ///
+    /// Deletes a section.
+    DeleteSection { key: String },
+    /// Переименовывает секцию.
+    ///
+    /// Renames a section.
+    RenameSection { from: String, to: String },
+    /// Переименовывает переменную.
+    ///
+    /// Renames a variable.
+    RenameVar { key: String, from: String, to: String },
+    /// Переносит переменную в другую секцию.
+    ///
+    /// Moves a variable to another section.
+    Move { from: String, to: String, var: String },
+    /// Предусловие: переменная имеет значение `val` (`None` — отсутствует).
+    ///
+    /// Precondition: the variable has the value `val` (`None` means absent).
+    Test { key: String, var: String, val: Option<String> },
+}
+
+impl fmt::Debug for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Set { key, var, .. } => f.debug_struct("Set").field("key", key).field("var", var).field("val", &secret::REDACTED).finish(),
+            Op::Delete { key, var } => f.debug_struct("Delete").field("key", key).field("var", var).finish(),
+            Op::AddSection { key } => f.debug_struct("AddSection").field("key", key).finish(),
+            Op::DeleteSection { key } => f.debug_struct("DeleteSection").field("key", key).finish(),
+            Op::RenameSection { from, to } => f.debug_struct("RenameSection").field("from", from).field("to", to).finish(),
+            Op::RenameVar { key, from, to } => f.debug_struct("RenameVar").field("key", key).field("from", from).field("to", to).finish(),
+            Op::Move { from, to, var } => f.debug_struct("Move").field("from", from).field("to", to).field("var", var).finish(),
+            Op::Test { key, var, val } => {
+                let val = val.as_ref().map(|_| secret::REDACTED);
+                f.debug_struct("Test").field("key", key).field("var", var).field("val", &val).finish()
+            }
+        }
+    }
+}
+
+/// Набор операций, применяемых к документу атомарно: либо все, либо ни одной.
+/// `Debug` скрывает значения (см. `Op`).
+///
+/// A set of operations applied to a document atomically: either all or none.
+/// `Debug` hides the values (see `Op`).
+///
+/// # Example
+/// ```no_run
+/// # use tnt::{Patch, TNT};
+/// let tnt = TNT::open("app.tnt").unwrap();
+/// let patch = Patch::new()
+///     .test("db", "port", Some("5432"))
+///     .set("db", "port", "6432")
+///     .delete("cache", "ttl")
+///     .rename_section("x", "y");
+///
+/// let rollback = tnt.apply(&patch).unwrap();
+/// tnt.apply(&rollback).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Patch {
+    ops: Vec<Op>,
+}
+
+impl Patch {
+    /// Создаёт пустой патч.
+    ///
+    /// Creates an empty patch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Операции патча в порядке применения.
+    ///
+    /// The patch operations in application order.
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Добавляет произвольную операцию.
+    ///
+    /// Appends an arbitrary operation.
+    pub fn push(mut self, op: Op) -> Self {
+        self.ops.push(op);
+        self
+    }
+
+    /// Добавляет операцию `set`.
+    ///
+    /// Appends a `set` operation.
+    pub fn set<T: std::fmt::Display, V: std::fmt::Display>(self, key: &str, var: T, val: V) -> Self {
+        self.push(Op::Set { key: key.to_string(), var: var.to_string(), val: val.to_string() })
+    }
+
+    /// Добавляет операцию `delete`.
+    ///
+    /// Appends a `delete` operation.
+    pub fn delete(self, key: &str, var: &str) -> Self {
+        self.push(Op::Delete { key: key.to_string(), var: var.to_string() })
+    }
+
+    /// Добавляет операцию `add_section`.
+    ///
+    /// Appends an `add_section` operation.
+    pub fn add_section(self, key: &str) -> Self {
+        self.push(Op::AddSection { key: key.to_string() })
+    }
+
+    /// Добавляет операцию `delete_section`.
+    ///
+    /// Appends a `delete_section` operation.
+    pub fn delete_section(self, key: &str) -> Self {
+        self.push(Op::DeleteSection { key: key.to_string() })
+    }
+
+    /// Добавляет операцию `rename_section`.
+    ///
+    /// Appends a `rename_section` operation.
+    pub fn rename_section(self, from: &str, to: &str) -> Self {
+        self.push(Op::RenameSection { from: from.to_string(), to: to.to_string() })
+    }
+
+    /// Добавляет операцию `rename_var`.
+    ///
+    /// Appends a `rename_var` operation.
+    pub fn rename_var(self, key: &str, from: &str, to: &str) -> Self {
+        self.push(Op::RenameVar { key: key.to_string(), from: from.to_string(), to: to.to_string() })
+    }
+
+    /// Добавляет операцию `move`.
+    ///
+    /// Appends a `move` operation.
+    pub fn move_var(self, from: &str, to: &str, var: &str) -> Self {
+        self.push(Op::Move { from: from.to_string(), to: to.to_string(), var: var.to_string() })
+    }
+
+    /// Добавляет предусловие `test`.
+    ///
+    /// Appends a `test` precondition.
+    pub fn test(self, key: &str, var: &str, val: Option<&str>) -> Self {
+        self.push(Op::Test { key: key.to_string(), var: var.to_string(), val: val.map(str::to_string) })
+    }
+
+    /// Применяет патч к документу. При любой ошибке документ не изменяется.
+    /// Возвращает обратный патч для отката: он восстанавливает значения,
+    /// но удалённые переменные возвращаются в конец своей секции.
+    ///
+    /// Applies the patch to a document. On any error the document is left unchanged.
+    /// Returns the inverse patch for rollback: it restores the values,
+    /// but deleted variables come back at the end of their section.
+    pub fn apply(&self, doc: &mut Document) -> Result<Patch, Error> {
+        let mut work = doc.clone();
+        let mut inverse: Vec<Op> = Vec::new();
+
+        for op in &self.ops {
+            match op {
+                Op::Set { key, var, val } => {
+                    match work.get(key, var) {
+                        Some(old) => inverse.push(Op::Set { key: key.clone(), var: var.clone(), val: stored(&work, key, var, old) }),
+                        None if work.sections().iter().any(|s| s.name() == key.trim()) => {
+                            inverse.push(Op::Delete { key: key.clone(), var: var.clone() })
+                        }
+                        None => inverse.push(Op::DeleteSection { key: key.clone() }),
+                    }
+
+                    work.set(key, var, val)?;
+                }
+                Op::Delete { key, var } => {
+                    let old = work
+                        .get(key, var)
+                        .map(|old| stored(&work, key, var, old))
+                        .ok_or_else(|| Error::VarNotFound { key: key.clone(), var: var.clone() })?;
+
+                    work.delete_var(key, var);
+                    inverse.push(Op::Set { key: key.clone(), var: var.clone(), val: old });
+                }
+                Op::AddSection { key } => {
+                    if work.sections().iter().any(|s| s.name() == key.trim()) {
+                        return Err(Error::KeyExists(key.clone()));
+                    }
+
+                    work.push_section(Section::new(key));
+                    inverse.push(Op::DeleteSection { key: key.clone() });
+                }
+                Op::DeleteSection { key } => {
+                    let entries: Vec<(String, String)> =
+                        work.entries(key).iter().map(|(var, val)| (var.clone(), stored(&work, key, var, val))).collect();
+
+                    if !work.delete_key(key) {
+                        return Err(Error::KeyNotFound(key.clone()));
+                    }
+
+                    // The inverse list is reversed at the end, so push the entries backwards.
+                    for (var, val) in entries.into_iter().rev() {
+                        inverse.push(Op::Set { key: key.clone(), var, val });
+                    }
+
+                    inverse.push(Op::AddSection { key: key.clone() });
+                }
+                Op::RenameSection { from, to } => {
+                    work.rename_section(from, to)?;
+                    inverse.push(Op::RenameSection { from: to.clone(), to: from.clone() });
+                }
+                Op::RenameVar { key, from, to } => {
+                    work.rename_var(key, from, to)?;
+                    inverse.push(Op::RenameVar { key: key.clone(), from: to.clone(), to: from.clone() });
+                }
+                Op::Move { from, to, var } => {
+                    let is_new = !work.sections().iter().any(|s| s.name() == to.trim());
+                    work.move_var(from, to, var)?;
+
+                    if is_new {
+                        inverse.push(Op::DeleteSection { key: to.clone() });
+                    }
+
+                    inverse.push(Op::Move { from: to.clone(), to: from.clone(), var: var.clone() });
+                }
+                Op::Test { key, var, val } => {
+                    let actual = work.get(key, var);
+
+                    if actual != val.as_deref() {
+                        let shown = |val: Option<&str>| match val {
+                            Some(_) if work.is_secret(key, var) => Some(secret::REDACTED.to_string()),
+                            val => val.map(str::to_string),
+                        };
+
+                        return Err(Error::TestFailed {
+                            key: key.clone(),
+                            var: var.clone(),
+                            expected: shown(val.as_deref()),
+                            actual: shown(actual),
+                        });
+                    }
+                }
+            }
+        }
+
+        *doc = work;
+        inverse.reverse();
+
+        Ok(Patch { ops: inverse })
+    }
+
+    /// Сериализует патч в формате TNT: по одной секции на операцию.
+    ///
+    /// Serializes the patch in TNT format: one section per operation.
+    pub fn to_tnt(&self) -> String {
+        let mut doc = Document::new();
+
+        for (i, op) in self.ops.iter().enumerate() {
+            let (name, fields) = fields(op);
+            let mut section = Section::new(&i.to_string());
+            section.push("op", name);
+
+            for (field, val) in fields {
+                if let Some(val) = val {
+                    section.push(field, val);
+                }
+            }
+
+            doc.push_section(section);
+        }
+
+        doc.render()
+    }
+
+    /// Разбирает патч из формата TNT (см. `to_tnt`).
+    ///
+    /// Parses a patch from TNT format (see `to_tnt`).
+    pub fn from_tnt(text: &str) -> Result<Patch, Error> {
+
+        let doc = Document::parse(text)?;
+        let mut ops = Vec::new();
+
+        for section in doc.sections() {
+            let get = |field: &str| section.entries().iter().find(|(v, _)| v == field).map(|(_, val)| val.clone());
+            let name = get("op").ok_or_else(|| Error::Parse(format!("section '{}' has no 'op'", section.name())))?;
+            ops.push(op(&name, get)?);
+        }
+
+        Ok(Patch { ops })
+    }
+
+    /// Сериализует патч в JSON: массив объектов с полем `op`.
+    ///
+    /// Serializes the patch as JSON: an array of objects with an `op` field.
+    pub fn to_json(&self) -> String {
+        let records: Vec<String> = self.ops.iter().map(op_to_json).collect();
+
+        if records.is_empty() {
+            return "[]".to_string();
+        }
+
+        format!("[\n\t{}\n]", records.join(",\n\t"))
+    }
+
+    /// Разбирает патч из JSON (см. `to_json`).
+    ///
+    /// Parses a patch from JSON (see `to_json`).
+    pub fn from_json(text: &str) -> Result<Patch, Error> {
+        let records = match json::parse(text)? {
+            Value::Array(records) => records,
+            _ => return Err(Error::Parse("a patch must be a JSON array".to_string())),
+        };
+
+        let mut ops = Vec::new();
+
+        for record in &records {
+            let get = |field: &str| record.get(field).and_then(Value::as_str).map(str::to_string);
+            let name = get("op").ok_or_else(|| Error::Parse("a patch operation has no 'op'".to_string()))?;
+            ops.push(op(&name, get)?);
+        }
+
+        Ok(Patch { ops })
+    }
+}
+
+/// Сериализует одну операцию в JSON-объект в одну строку.
+pub(crate) fn op_to_json(op: &Op) -> String {
+    let (name, fields) = fields(op);
+    let mut record = format!("{{\"op\": \"{}\"", name);
+
+    for (field, val) in fields {
+        match val {
+            Some(val) => record.push_str(&format!(", \"{}\": \"{}\"", field, json::escape(val))),
+            None => record.push_str(&format!(", \"{}\": null", field)),
+        }
+    }
+
+    record.push('}');
+    record
+}
+
+pub(crate) fn stored(doc: &Document, key: &str, var: &str, val: &str) -> String {
+    if doc.is_marked(key, var) { format!("{} {}", secret::MARKER, val) } else { val.to_string() }
+}
+
+fn fields(op: &Op) -> (&'static str, Vec<(&'static str, Option<&str>)>) {
+    match op {
+        Op::Set { key, var, val } => ("set", vec![("section", Some(key)), ("var", Some(var)), ("value", Some(val))]),
+        Op::Delete { key, var } => ("delete", vec![("section", Some(key)), ("var", Some(var))]),
+        Op::AddSection { key } => ("add_section", vec![("section", Some(key))]),
+        Op::DeleteSection { key } => ("delete_section", vec![("section", Some(key))]),
+        Op::RenameSection { from, to } => ("rename_section", vec![("from", Some(from)), ("to", Some(to))]),
+        Op::RenameVar { key, from, to } => ("rename_var", vec![("section", Some(key)), ("from", Some(from)), ("to", Some(to))]),
+        Op::Move { from, to, var } => ("move", vec![("from", Some(from)), ("to", Some(to)), ("var", Some(var))]),
+        Op::Test { key, var, val } => ("test", vec![("section", Some(key)), ("var", Some(var)), ("value", val.as_deref())]),
+    }
+}
+
+fn op<F: Fn(&str) -> Option<String>>(name: &str, get: F) -> Result<Op, Error> {
+    let field = |field: &str| get(field).ok_or_else(|| Error::Parse(format!("'{}' operation has no '{}'", name, field)));
+
+    let op = match name {
+        "set" => Op::Set { key: field("section")?, var: field("var")?, val: field("value")? },
+        "delete" => Op::Delete { key: field("section")?, var: field("var")? },
+        "add_section" => Op::AddSection { key: field("section")? },
+        "delete_section" => Op::DeleteSection { key: field("section")? },
+        "rename_section" => Op::RenameSection { from: field("from")?, to: field("to")? },
+        "rename_var" => Op::RenameVar { key: field("section")?, from: field("from")?, to: field("to")? },
+        "move" => Op::Move { from: field("from")?, to: field("to")?, var: field("var")? },
+        "test" => Op::Test { key: field("section")?, var: field("var")?, val: get("value") },
+        _ => return Err(Error::Parse(format!("unknown patch operation '{}'", name))),
+    };
+
+    Ok(op)
+}
+
+impl<S: WritableStorage> TNT<S> {
+    /// Атомарно применяет патч к файлу: либо все операции, либо ни одной.
+    /// Возвращает обратный патч для отката.
+    ///
+    /// # Аргументы
+    /// * `patch` - Патч для применения
+    ///
+    /// Atomically applies a patch to the file: either all operations or none.
+    /// Returns the inverse patch for rollback.
+    ///
+    /// # Arguments
+    /// * `patch` - Patch to apply
+    pub fn apply(&self, patch: &Patch) -> Result<Patch, Error> {
+        let mut doc = self.load()?;
+        let inverse = patch.apply(&mut doc)?;
+        self.save_as("apply", &doc)?;
+
+        Ok(inverse)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::document::{Document, Section};
+use crate::error::Error;
+use crate::secret;
+use crate::storage::Storage;
+use crate::tnt::{self, TNT};
+
+/// Представление файла для одного профиля (окружения).
+///
+/// Секция `(db @prod) { ... }` — это вариант секции `(db)` для профиля `prod`.
+/// В представлении профиля значения из `(db @prod)` переопределяют значения из `(db)`,
+/// а отсутствующие берутся из базовой секции. Секции других профилей не видны.
+///
+/// A view of the file for a single profile (environment).
+///
+/// The section `(db @prod) { ... }` is the `prod` profile variant of the `(db)` section.
+/// In a profile view the values from `(db @prod)` override those from `(db)`,
+/// and missing ones fall back to the base section. Sections of other profiles are not visible.
+///
+/// # Example
+/// ```no_run
+/// # use tnt::TNT;
+/// let tnt = TNT::open("app.tnt").unwrap();
+/// let prod = tnt.with_profile("prod").unwrap();
+///
+/// println!("{:?}", prod.get("db", "host"));
+/// prod.to_toml("app.prod").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Profile {
+    name: String,
+    doc: Document,
+}
+
+impl<S: Storage> TNT<S> {
+    /// Загружает файл и возвращает его представление для профиля.
+    ///
+    /// # Аргументы
+    /// * `profile` - Имя профиля (`dev`, `prod`, ...)
+    ///
+    /// Loads the file and returns its view for a profile.
+    ///
+    /// # Arguments
+    /// * `profile` - Profile name (`dev`, `prod`, ...)
+    pub fn with_profile(&self, profile: &str) -> Result<Profile, Error> {
+        let doc = self.load()?.with_profile(profile);
+
+        Ok(Profile { name: profile.trim().to_string(), doc })
+    }
+}
+
+impl Document {
+    /// Имена всех профилей, встречающихся в документе, в порядке первого появления.
+    ///
+    /// The names of every profile in the document, in order of first appearance.
+    pub fn profiles(&self) -> Vec<&str> {
+        let mut profiles: Vec<&str> = Vec::new();
+
+        for profile in self.sections().iter().filter_map(|s| s.profile()) {
+            if !profiles.contains(&profile) {
+                profiles.push(profile);
+            }
+        }
+
+        profiles
+    }
+
+    /// Сводит документ к одному профилю: базовые секции с наложенными значениями профиля.
+    /// Секции профиля без базовой секции сохраняются под базовым именем, секции других профилей отбрасываются.
+    ///
+    /// # Аргументы
+    /// * `profile` - Имя профиля
+    ///
+    /// Flattens the document to a single profile: base sections with the profile values laid over them.
+    /// Profile sections without a base section are kept under the base name, sections of other profiles are dropped.
+    ///
+    /// # Arguments
+    /// * `profile` - Profile name
+    pub fn with_profile(&self, profile: &str) -> Document {
+        let mut doc = Document::new();
+
+        if let Some(version) = self.version() {
+            doc.set_version(version);
+        }
+
+        for path in self.includes() {
+            doc.add_include(path);
+        }
+
+        for section in self.sections().iter().filter(|s| s.profile().is_none()) {
+            copy(self, &mut doc, section.name(), section.name());
+        }
+
+        for section in self.sections().iter().filter(|s| s.profile() == Some(profile.trim())) {
+            copy(self, &mut doc, section.name(), section.base_name());
+        }
+
+        doc
+    }
+}
+
+fn copy(from: &Document, to: &mut Document, key: &str, target: &str) {
+    if !to.sections().iter().any(|s| s.name() == target) {
+        to.push_section(Section::new(target));
+    }
+
+    for (var, val) in from.entries(key) {
+        if from.is_marked(key, var) {
+            to.put(target, var, &format!("{} {}", secret::MARKER, val));
+        } else {
+            to.put(target, var, val);
+        }
+    }
+}
+
+impl Profile {
+    /// Имя профиля.
+    ///
+    /// The profile name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Документ, сведённый к профилю.
+    ///
+    /// The document flattened to the profile.
+    pub fn document(&self) -> &Document {
+        &self.doc
+    }
+
+    /// Получает значение переменной с учётом профиля и подстановкой ссылок `${...}`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя базовой секции (ключа), без профиля
+    /// * `var` - Имя переменной
+    ///
+    /// Gets the value of a variable for the profile, with `${...}` references substituted.
+    ///
+    /// # Arguments
+    /// * `key` - Base section (key) name, without the profile
+    /// * `var` - Variable name
+    pub fn get(&self, key: &str, var: &str) -> Result<Option<String>, Error> {
+        self.doc.resolve(key, var)
+    }
+
+    /// Экспортирует профиль в формате TOML.
+    ///
+    /// # Аргументы
+    /// * `filename` - Имя файла (без расширения), куда будет сохранён TOML.
+    ///
+    /// Exports the profile in TOML format.
+    ///
+    /// # Arguments
+    /// * `filename` - File name (without extension) where TOML will be saved.
+    pub fn to_toml(&self, filename: &str) -> std::io::Result<()> {
+        tnt::export_toml(&self.doc, filename, false)
+    }
+
+    /// Экспортирует профиль в формате JSON.
+    ///
+    /// # Аргументы
+    /// * `filename` - Имя файла (без расширения), куда будет сохранён JSON.
+    ///
+    /// Exports the profile in JSON format.
+    ///
+    /// # Arguments
+    /// * `filename` - File name (without extension) where JSON will be saved.
+    pub fn to_json(&self, filename: &str) -> std::io::Result<()> {
+        tnt::export_json(&self.doc, filename, false)
+    }
+}
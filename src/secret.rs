@@ -0,0 +1,145 @@
+/// Маркер секретного значения: `password=!secret hunter2`.
+///
+/// The secret value marker: `password=!secret hunter2`.
+pub const MARKER: &str = "!secret";
+
+/// Замена секретного значения при выводе.
+///
+/// The replacement for a secret value in output.
+pub const REDACTED: &str = "***";
+
+const SECRET_NAMES: [&str; 6] = ["password", "passwd", "secret", "token", "api_key", "private_key"];
+
+/// Возвращает `true`, если имя переменной по соглашению обозначает секрет
+/// (содержит `password`, `passwd`, `secret`, `token`, `api_key` или `private_key`).
+///
+/// Returns `true` if the variable name denotes a secret by convention
+/// (it contains `password`, `passwd`, `secret`, `token`, `api_key` or `private_key`).
+pub fn is_secret_name(var: &str) -> bool {
+    let var = var.trim().to_lowercase();
+
+    SECRET_NAMES.iter().any(|name| var.contains(name))
+}
+
+/// Отделяет маркер `!secret` от значения. Возвращает значение без маркера, если он был.
+pub(crate) fn strip_marker(val: &str) -> Option<&str> {
+    val.strip_prefix(MARKER).map(|rest| rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+/// Значение без маркера `!secret`.
+pub(crate) fn plain(val: &str) -> &str {
+    strip_marker(val).unwrap_or(val)
+}
+
+#[cfg(feature = "encryption")]
+pub use self::encryption::SecretKey;
+
+#[cfg(feature = "encryption")]
+mod encryption {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::Path;
+
+    use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+    use crate::error::Error;
+
+    const PREFIX: &str = "enc:";
+
+    /// Локальный ключ для хранения секретов в зашифрованном виде (ChaCha20-Poly1305).
+    /// Зашифрованное значение записывается как `!secret enc:<hex>`.
+    ///
+    /// A local key for storing secrets encrypted at rest (ChaCha20-Poly1305).
+    /// An encrypted value is written as `!secret enc:<hex>`.
+    pub struct SecretKey {
+        cipher: ChaCha20Poly1305,
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn from_hex(hex: &str) -> Result<Vec<u8>, Error> {
+        let hex = hex.trim();
+
+        if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+            return Err(Error::Crypto("invalid hex".to_string()));
+        }
+
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| Error::Crypto("invalid hex".to_string())))
+            .collect()
+    }
+
+    impl SecretKey {
+        /// Создаёт новый случайный ключ и записывает его в файл. Файл не должен существовать.
+        ///
+        /// Generates a new random key and writes it to a file. The file must not exist.
+        pub fn generate<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+            let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+            let mut file = options.open(path)?;
+            writeln!(file, "{}", to_hex(&key))?;
+
+            Ok(Self { cipher: ChaCha20Poly1305::new(&key) })
+        }
+
+        /// Загружает ключ из файла.
+        ///
+        /// Loads a key from a file.
+        pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+            let bytes = from_hex(&fs::read_to_string(path)?)?;
+
+            if bytes.len() != 32 {
+                return Err(Error::Crypto("the key must be 32 bytes".to_string()));
+            }
+
+            Ok(Self { cipher: ChaCha20Poly1305::new(Key::from_slice(&bytes)) })
+        }
+
+        /// Шифрует значение в вид `enc:<hex>`.
+        ///
+        /// Encrypts a value into the `enc:<hex>` form.
+        pub fn encrypt(&self, val: &str) -> Result<String, Error> {
+            let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+            let mut data = nonce.to_vec();
+            let cipher_text = self
+                .cipher
+                .encrypt(&nonce, val.as_bytes())
+                .map_err(|_| Error::Crypto("encryption failed".to_string()))?;
+            data.extend(cipher_text);
+
+            Ok(format!("{}{}", PREFIX, to_hex(&data)))
+        }
+
+        /// Расшифровывает значение вида `enc:<hex>`. Прочие значения возвращаются как есть.
+        ///
+        /// Decrypts an `enc:<hex>` value. Other values are returned as is.
+        pub fn decrypt(&self, val: &str) -> Result<String, Error> {
+            let Some(hex) = val.strip_prefix(PREFIX) else {
+                return Ok(val.to_string());
+            };
+
+            let data = from_hex(hex)?;
+
+            if data.len() < 12 {
+                return Err(Error::Crypto("the encrypted value is too short".to_string()));
+            }
+
+            let (nonce, cipher_text) = data.split_at(12);
+            let plain = self
+                .cipher
+                .decrypt(Nonce::from_slice(nonce), cipher_text)
+                .map_err(|_| Error::Crypto("decryption failed (wrong key?)".to_string()))?;
+
+            String::from_utf8(plain).map_err(|_| Error::Crypto("the decrypted value is not UTF-8".to_string()))
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+
+use crate::document::Document;
+use crate::error::Error;
+use crate::storage::{FileStorage, WritableStorage};
+use crate::tnt::TNT;
+
+/// Неизменяемый снимок документа с номером версии.
+///
+/// An immutable snapshot of the document with a version number.
+#[derive(Debug)]
+pub struct Snapshot {
+    version: u64,
+    doc: Document,
+}
+
+impl Snapshot {
+    /// Номер версии. Растёт на единицу при каждой опубликованной записи или перезагрузке.
+    ///
+    /// The version number. It grows by one with every published write or reload.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Документ этого снимка.
+    ///
+    /// The document of this snapshot.
+    pub fn document(&self) -> &Document {
+        &self.doc
+    }
+
+    /// Получает значение переменной без подстановки ссылок.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Gets the value of a variable without substituting references.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    pub fn get(&self, key: &str, var: &str) -> Option<&str> {
+        self.doc.get(key, var)
+    }
+}
+
+/// Разделяемая между потоками конфигурация. Читатели получают текущий снимок без блокировок,
+/// писатели по очереди изменяют копию, атомарно сохраняют её и только после успешной записи
+/// публикуют новый снимок. Клоны разделяют одно и то же состояние.
+///
+/// A configuration shared between threads. Readers get the current snapshot without locking,
+/// writers take turns changing a copy, save it atomically and publish the new snapshot only
+/// after the write succeeds. Clones share the same state.
+///
+/// # Example
+/// ```
+/// # use tnt::{MemoryStorage, TNT};
+/// let config = TNT::with_storage(MemoryStorage::new("(db) {\n\tport=5432\n}\n")).shared().unwrap();
+///
+/// let before = config.snapshot();
+/// config.update(|doc| doc.set("db", "port", 6432)).unwrap();
+///
+/// assert_eq!(before.get("db", "port"), Some("5432"));
+/// assert_eq!(config.snapshot().get("db", "port"), Some("6432"));
+/// assert_eq!(config.version(), before.version() + 1);
+/// ```
+#[derive(Debug)]
+pub struct SharedConfig<S: WritableStorage = FileStorage> {
+    tnt: Arc<TNT<S>>,
+    current: Arc<ArcSwap<Snapshot>>,
+    writer: Arc<Mutex<()>>,
+}
+
+impl<S: WritableStorage> Clone for SharedConfig<S> {
+    fn clone(&self) -> Self {
+        Self { tnt: Arc::clone(&self.tnt), current: Arc::clone(&self.current), writer: Arc::clone(&self.writer) }
+    }
+}
+
+impl<S: WritableStorage> TNT<S> {
+    /// Загружает документ и создаёт разделяемую конфигурацию с версией 0.
+    ///
+    /// Loads the document and creates a shared configuration at version 0.
+    pub fn shared(self) -> Result<SharedConfig<S>, Error> {
+        let doc = self.load()?;
+
+        Ok(SharedConfig {
+            tnt: Arc::new(self),
+            current: Arc::new(ArcSwap::from_pointee(Snapshot { version: 0, doc })),
+            writer: Arc::new(Mutex::new(())),
+        })
+    }
+}
+
+impl<S: WritableStorage> SharedConfig<S> {
+    /// Текущий снимок. Не блокирует и не читает файл; снимок остаётся неизменным,
+    /// даже если после этого будет опубликована новая версия.
+    ///
+    /// The current snapshot. It does not lock or read the file; the snapshot stays unchanged
+    /// even if a new version is published afterwards.
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.current.load_full()
+    }
+
+    /// Номер текущей версии.
+    ///
+    /// The current version number.
+    pub fn version(&self) -> u64 {
+        self.current.load().version
+    }
+
+    /// Изменяет копию текущего документа, атомарно сохраняет её и публикует новую версию.
+    /// Если `f` вернула ошибку или сохранение не удалось, читатели продолжают видеть прежний снимок.
+    ///
+    /// # Аргументы
+    /// * `f` - Функция, изменяющая документ
+    ///
+    /// Changes a copy of the current document, saves it atomically and publishes a new version.
+    /// If `f` returns an error or the save fails, readers keep seeing the previous snapshot.
+    ///
+    /// # Arguments
+    /// * `f` - Function that changes the document
+    pub fn update<F>(&self, f: F) -> Result<u64, Error>
+    where
+        F: FnOnce(&mut Document) -> Result<(), Error>,
+    {
+        self.update_as("update", f)
+    }
+
+    fn update_as<F>(&self, operation: &str, f: F) -> Result<u64, Error>
+    where
+        F: FnOnce(&mut Document) -> Result<(), Error>,
+    {
+        let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let current = self.current.load_full();
+
+        let mut doc = current.doc.clone();
+        f(&mut doc)?;
+        self.tnt.save_as(operation, &doc)?;
+
+        Ok(self.publish(current.version, doc))
+    }
+
+    /// Устанавливает значение переменной и публикует новую версию. См. `update`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
+    ///
+    /// Sets the value of a variable and publishes a new version. See `update`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    pub fn set<V: std::fmt::Display>(&self, key: &str, var: &str, val: V) -> Result<u64, Error> {
+        self.update_as("set", |doc| doc.set(key, var, val))
+    }
+
+    /// Перечитывает хранилище и публикует новую версию, если документ изменился извне.
+    ///
+    /// Re-reads the storage and publishes a new version if the document was changed externally.
+    pub fn reload(&self) -> Result<u64, Error> {
+        let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let current = self.current.load_full();
+        let doc = self.tnt.load()?;
+
+        if doc == current.doc {
+            // Same content, but the file itself changed (whitespace, comments): keep the version
+            // and adopt the new origin so that the next save does not report a conflict.
+            if let Some(origin) = doc.origin() {
+                current.doc.set_origin(origin);
+            }
+
+            return Ok(current.version);
+        }
+
+        Ok(self.publish(current.version, doc))
+    }
+
+    fn publish(&self, version: u64, doc: Document) -> u64 {
+        let version = version + 1;
+        self.current.store(Arc::new(Snapshot { version, doc }));
+
+        version
+    }
+}
@@ -0,0 +1,305 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::SystemTime;
+
+/// Хранилище текста TNT-документа. `TNT` выполняет все чтения и записи через него,
+/// поэтому документ может жить в файле, в памяти или во внешнем хранилище.
+///
+/// A store for the text of a TNT document. `TNT` performs every read and write through it,
+/// so a document can live in a file, in memory or in an external blob store.
+///
+/// # Example
+/// ```
+/// # use tnt::{MemoryStorage, TNT};
+/// let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\tport=5432\n}\n"));
+/// tnt.set("db", "host", "localhost").unwrap();
+///
+/// assert_eq!(tnt.get("db", "host").unwrap(), "localhost");
+/// ```
+pub trait Storage {
+    /// Читает весь текст документа.
+    ///
+    /// Reads the whole text of the document.
+    fn read(&self) -> io::Result<String>;
+
+    /// Время изменения и размер, если хранилище может сообщить их без чтения текста.
+    /// Наблюдатель (`TNT::watch`) использует их, чтобы не читать неизменившийся документ.
+    ///
+    /// The modification time and size, if the store can report them without reading the text.
+    /// The watcher (`TNT::watch`) uses them to skip reading an unchanged document.
+    fn stamp(&self) -> io::Result<Option<(SystemTime, u64)>> {
+        Ok(None)
+    }
+}
+
+/// Хранилище, в которое можно писать. Методы `TNT`, изменяющие документ, доступны
+/// только поверх такого хранилища, поэтому дескриптор `ReadOnly` не даёт их вызвать.
+///
+/// A store that can be written to. The `TNT` methods that change the document are only
+/// available on top of such a store, so a `ReadOnly` handle cannot call them.
+pub trait WritableStorage: Storage {
+    /// Атомарно заменяет весь текст документа: читатель видит либо старый текст, либо новый.
+    ///
+    /// Atomically replaces the whole text of the document: a reader sees either the old text or the new one.
+    fn write(&self, text: &str) -> io::Result<()>;
+
+    /// Берёт эксклюзивную блокировку на время чтения-изменения-записи. `TNT` берёт её вокруг
+    /// каждой изменяющей операции. По умолчанию ничего не делает.
+    ///
+    /// Takes an exclusive lock for a read-modify-write cycle. `TNT` takes it around
+    /// every mutating operation. Does nothing by default.
+    fn lock(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Снимает блокировку, взятую `lock`. По умолчанию ничего не делает.
+    ///
+    /// Releases the lock taken by `lock`. Does nothing by default.
+    fn unlock(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Хранилище в файле. Запись атомарна: текст пишется в уникальный временный файл в том же каталоге
+/// и переименовывается поверх файла. Права доступа файла сохраняются, а если файл — символическая
+/// ссылка, заменяется файл, на который она указывает. Блокировка берётся на файл `{filename}.lock`.
+/// Он создаётся при первой изменяющей операции и никогда не удаляется: если удалить его, пока другой
+/// процесс ждёт блокировку, два процесса окажутся заблокированы на разных файлах. Удалять его вручную
+/// можно, только когда с файлом никто не работает.
+///
+/// File storage. Writes are atomic: the text goes to a unique temporary file in the same directory,
+/// which is renamed over the file. The file's permissions are kept, and if the file is a symbolic link,
+/// the file it points to is replaced. The lock is taken on the `{filename}.lock` file.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    filename: String,
+    lock: Arc<Mutex<Option<File>>>,
+}
+
+impl FileStorage {
+    /// Создаёт хранилище для указанного файла. Файл при этом не создаётся.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к файлу
+    ///
+    /// Creates storage for the given file. The file itself is not created.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self { filename: path.as_ref().to_string_lossy().into_owned(), lock: Arc::new(Mutex::new(None)) }
+    }
+
+    /// Имя файла.
+    ///
+    /// The file name.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self) -> io::Result<String> {
+        fs::read_to_string(&self.filename)
+    }
+
+    fn stamp(&self) -> io::Result<Option<(SystemTime, u64)>> {
+        let meta = fs::metadata(&self.filename)?;
+
+        Ok(meta.modified().ok().map(|modified| (modified, meta.len())))
+    }
+}
+
+impl WritableStorage for FileStorage {
+    fn write(&self, text: &str) -> io::Result<()> {
+        let target = write_target(Path::new(&self.filename))?;
+        let permissions = fs::metadata(&target).ok().map(|meta| meta.permissions());
+
+        let (tmp_path, mut file) = loop {
+            let tmp_path = temp_path(&target);
+
+            match OpenOptions::new().write(true).create_new(true).open(&tmp_path) {
+                Ok(file) => break (tmp_path, file),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        };
+
+        let result = (|| {
+            if let Some(permissions) = permissions {
+                file.set_permissions(permissions)?;
+            }
+
+            file.write_all(text.as_bytes())?;
+            file.sync_all()?;
+
+            fs::rename(&tmp_path, &target)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        result
+    }
+
+    fn lock(&self) -> io::Result<()> {
+        let file = OpenOptions::new().write(true).truncate(false).create(true).open(format!("{}.lock", self.filename))?;
+        file.lock()?;
+        *self.lock.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+
+        Ok(())
+    }
+
+    fn unlock(&self) -> io::Result<()> {
+        match self.lock.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(file) => file.unlock(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Файл, который на самом деле заменяет запись в `path`: цель символической ссылки или сам `path`.
+fn write_target(path: &Path) -> io::Result<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(target) => Ok(target),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(path.to_path_buf()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Уникальное в пределах процесса имя временного файла рядом с `target`.
+pub(crate) fn temp_path(target: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    target.with_file_name(format!(".{}.{}.{}.tmp", name, std::process::id(), id))
+}
+
+/// Хранилище в памяти. Клоны разделяют один и тот же текст и одну блокировку.
+///
+/// In-memory storage. Clones share the same text and the same lock.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    text: Arc<Mutex<String>>,
+    lock: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl fmt::Debug for MemoryStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.text.lock().unwrap_or_else(|e| e.into_inner()).len();
+
+        f.debug_struct("MemoryStorage").field("len", &len).finish_non_exhaustive()
+    }
+}
+
+impl MemoryStorage {
+    /// Создаёт хранилище с начальным текстом.
+    ///
+    /// # Аргументы
+    /// * `text` - Начальный текст в формате TNT
+    ///
+    /// Creates storage with the initial text.
+    ///
+    /// # Arguments
+    /// * `text` - Initial text in TNT format
+    pub fn new(text: &str) -> Self {
+        Self { text: Arc::new(Mutex::new(text.to_string())), lock: Arc::default() }
+    }
+
+    /// Текущий текст документа.
+    ///
+    /// The current text of the document.
+    pub fn text(&self) -> String {
+        self.text.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self) -> io::Result<String> {
+        Ok(self.text())
+    }
+}
+
+impl WritableStorage for MemoryStorage {
+    fn write(&self, text: &str) -> io::Result<()> {
+        *self.text.lock().unwrap_or_else(|e| e.into_inner()) = text.to_string();
+
+        Ok(())
+    }
+
+    fn lock(&self) -> io::Result<()> {
+        let (locked, released) = &*self.lock;
+        let mut locked = locked.lock().unwrap_or_else(|e| e.into_inner());
+
+        while *locked {
+            locked = released.wait(locked).unwrap_or_else(|e| e.into_inner());
+        }
+
+        *locked = true;
+
+        Ok(())
+    }
+
+    fn unlock(&self) -> io::Result<()> {
+        let (locked, released) = &*self.lock;
+        *locked.lock().unwrap_or_else(|e| e.into_inner()) = false;
+        released.notify_one();
+
+        Ok(())
+    }
+}
+
+/// Хранилище только для чтения поверх другого хранилища. `TNT` поверх него
+/// не имеет методов записи: попытка изменить документ не компилируется.
+///
+/// Read-only storage on top of another store. A `TNT` on top of it has no
+/// write methods: an attempt to change the document does not compile.
+///
+/// ```compile_fail
+/// # use tnt::{MemoryStorage, ReadOnly, TNT};
+/// let tnt = TNT::with_storage(ReadOnly::new(MemoryStorage::new("")));
+/// tnt.set("db", "host", "localhost").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadOnly<S: Storage> {
+    inner: S,
+}
+
+impl<S: Storage> ReadOnly<S> {
+    /// Закрывает хранилище на запись.
+    ///
+    /// # Аргументы
+    /// * `inner` - Исходное хранилище
+    ///
+    /// Closes the store for writing.
+    ///
+    /// # Arguments
+    /// * `inner` - Underlying store
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    /// Исходное хранилище.
+    ///
+    /// The underlying store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Storage> Storage for ReadOnly<S> {
+    fn read(&self) -> io::Result<String> {
+        self.inner.read()
+    }
+
+    fn stamp(&self) -> io::Result<Option<(SystemTime, u64)>> {
+        self.inner.stamp()
+    }
+}
@@ -1,8 +1,31 @@
-use std::fs::{self, File, OpenOptions};
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::audit::{AuditSink, Auditor};
+use crate::diff;
+use crate::document::{self, Document, DuplicatePolicy, Origin, Section};
+use crate::error::Error;
+use crate::interpolate;
+use crate::json;
+use crate::list;
+use crate::secret;
+use crate::open::OpenOptions;
+use crate::storage::{FileStorage, ReadOnly, Storage, WritableStorage};
+
+#[derive(Debug, Clone)]
+pub struct TNT<S: Storage = FileStorage> {
+    storage: S,
+    duplicates: DuplicatePolicy,
+    audit: Option<Auditor>,
+}
 
-pub struct TNT {
-    filename: String,
+struct Block {
+    key: String,
+    open: usize,
+    close: usize,
+    entries: Vec<(String, String, usize)>,
 }
 
 impl TNT {
@@ -12,41 +35,143 @@ impl TNT {
     /// # Аргументы
     /// * `filename` - Имя файла для хранения данных.
     ///
-    /// # Пример
-    /// ```
-    /// let tnt = TNT::connect("data.txt");
-    /// ```
-    ///
     /// Creates a new TNT instance associated with the specified file.
     /// If the file does not exist, it will be created.
     ///
     /// # Arguments
     /// * `filename` - The name of the file to store data.
-    ///
-    /// # Example
-    /// ```
-    /// let tnt = TNT::connect("data.txt");
-    /// ```
+    #[deprecated(note = "silently creates missing files and panics on failure; use `TNT::open` or `OpenOptions::new().create().open`")]
     pub fn connect(filename: &str) -> Self {
 
         if fs::metadata(filename).is_err() {
             File::create(filename).expect("File creation error!");
         }
 
-        Self { filename: filename.to_string()}
+        Self::with_storage(FileStorage::new(filename))
+    }
+
+    /// Открывает существующий файл для чтения и записи. Чтобы создать файл
+    /// или открыть его только для чтения, используйте `OpenOptions`.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к файлу
+    ///
+    /// # Пример
+    /// ```no_run
+    /// # use tnt::TNT;
+    /// let tnt = TNT::open("data.txt").unwrap();
+    /// ```
+    ///
+    /// Opens an existing file for reading and writing. To create the file
+    /// or open it read-only, use `OpenOptions`.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use tnt::TNT;
+    /// let tnt = TNT::open("data.txt").unwrap();
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        OpenOptions::new().open(path)
+    }
+
+    /// Открывает существующий файл только для чтения. У полученного экземпляра нет методов записи.
+    ///
+    /// # Аргументы
+    /// * `path` - Путь к файлу
+    ///
+    /// Opens an existing file read-only. The returned instance has no write methods.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<TNT<ReadOnly<FileStorage>>, Error> {
+        OpenOptions::new().open_read_only(path)
+    }
+
+    /// Возвращает имя файла, с которым связан экземпляр.
+    ///
+    /// Returns the name of the file this instance is associated with.
+    pub fn filename(&self) -> &str {
+        self.storage.filename()
+    }
+}
+
+impl<S: Storage> TNT<S> {
+    /// Создаёт экземпляр TNT поверх произвольного хранилища (см. `Storage`).
+    ///
+    /// # Аргументы
+    /// * `storage` - Хранилище текста документа
+    ///
+    /// Creates a TNT instance on top of any storage (see `Storage`).
+    ///
+    /// # Arguments
+    /// * `storage` - Storage for the document text
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage, duplicates: DuplicatePolicy::default(), audit: None }
+    }
+
+    /// Хранилище, через которое работает экземпляр.
+    ///
+    /// The storage this instance works through.
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Задаёт правило обработки повторяющихся секций и переменных.
+    /// Правило одинаково применяется при чтении, изменении и загрузке документа.
+    ///
+    /// # Аргументы
+    /// * `policy` - Правило обработки повторов
+    ///
+    /// # Пример
+    /// ```no_run
+    /// # use tnt::{DuplicatePolicy, TNT};
+    /// let tnt = TNT::open("data.txt").unwrap().duplicates(DuplicatePolicy::Merge);
+    /// ```
+    ///
+    /// Sets the policy for repeated sections and variables.
+    /// The policy applies the same way to reads, modifications and document loading.
+    ///
+    /// # Arguments
+    /// * `policy` - Duplicate policy
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use tnt::{DuplicatePolicy, TNT};
+    /// let tnt = TNT::open("data.txt").unwrap().duplicates(DuplicatePolicy::Merge);
+    /// ```
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Возвращает правило обработки повторов.
+    ///
+    /// Returns the duplicate policy.
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicates
+    }
+
+    pub(crate) fn auditor(&self) -> Option<&Auditor> {
+        self.audit.as_ref()
+    }
+
+    pub(crate) fn with_auditor(mut self, audit: Option<Auditor>) -> Self {
+        self.audit = audit;
+        self
     }
 
     fn is_ccf(&self) -> std::io::Result<bool> {
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
+        let source = self.storage.read()?;
         let mut is_good = false;
         let mut brack = 0;
         let mut rev_brack = 0;
         let mut braces = 0;
         let mut rev_braces = 0;
 
-        for line in reader.lines() {
-            let line = line?;
+        for line in source.lines() {
 
             if line.find('(').is_some() {
                 brack += 1;
@@ -73,175 +198,145 @@ impl TNT {
     }
 
     fn get_var_line(&self, key: &str, var: &str) -> std::io::Result<i32> {
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let mut is_key = false;
-        let mut var_line: i32 = -1;
+        let blocks = self.read_blocks()?;
 
-        for (i, line) in reader.lines().enumerate() {
-            let line = line?;
-
-            if line.find('(').is_some() {
-                let cleaned_line = line.replace(['(', ')', '{'], "");
-                let k = cleaned_line.trim();
-
-                if k == key {
-                    is_key = true;
-                    continue;
-                }
-            }
-
-            if is_key && line.find('}').is_some() {
-                break;
-            }
+        Ok(self.find_var(&blocks, key, var).map(|(i, _)| i as i32).unwrap_or(-1))
+    }
 
+    fn scope<'a>(&self, blocks: &'a [Block], key: &str) -> Vec<&'a Block> {
+        let mut matching = blocks.iter().filter(|b| b.key == key.trim());
 
-            if is_key {
-                let fmt_line = line.trim();
-                let var_from_line = fmt_line.split('=').next().unwrap_or(fmt_line);
+        match self.duplicates {
+            DuplicatePolicy::FirstWins => matching.take(1).collect(),
+            DuplicatePolicy::LastWins => matching.next_back().into_iter().collect(),
+            DuplicatePolicy::Merge | DuplicatePolicy::Error => matching.collect(),
+        }
+    }
 
-                if var_from_line == var.trim() {
-                    var_line = i as i32;
-                    break;
+    fn var_lines(&self, blocks: &[Block], key: &str, var: &str) -> Vec<(usize, String)> {
+        self.scope(blocks, key)
+            .into_iter()
+            .flat_map(|b| b.entries.iter())
+            .filter(|(v, _, _)| v == var.trim())
+            .map(|(_, val, i)| (*i, val.clone()))
+            .collect()
+    }
 
-                }
-            }
+    fn find_var(&self, blocks: &[Block], key: &str, var: &str) -> Option<(usize, String)> {
+        let lines = self.var_lines(blocks, key, var);
 
+        match self.duplicates {
+            DuplicatePolicy::FirstWins => lines.into_iter().next(),
+            _ => lines.into_iter().last(),
         }
+    }
 
-        Ok(var_line)
+    fn target_block<'a>(&self, blocks: &'a [Block], key: &str) -> Option<&'a Block> {
+        self.scope(blocks, key).into_iter().next()
     }
 
-    fn is_var(&self, key: &str, var: &str) -> std::io::Result<bool> {
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let mut is_varible = false;
-        let mut is_found_key = false;
-        let mut var_val = String::new();
+    fn read_blocks(&self) -> Result<Vec<Block>, Error> {
+        let txt = self.storage.read()?;
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut is_key = false;
 
-        for line in reader.lines() {
-            let line = line?;
+        for (i, line) in txt.lines().enumerate() {
+            let fmt_line = line.trim();
 
-            if line.find('(').is_some() {
+            if !is_key && fmt_line.starts_with('@') {
+                continue;
+            }
+
+            if !is_key && line.contains('(') {
                 let cleaned_line = line.replace(['(', ')', '{'], "");
-                let k = cleaned_line.trim();
+                blocks.push(Block { key: cleaned_line.trim().to_string(), open: i, close: i, entries: Vec::new() });
+                is_key = true;
+                continue;
+            }
 
-                if k == key.trim() {
-                    is_found_key = true;
-                    continue;
+            if is_key && line.trim_start().starts_with('}') {
+                if let Some(block) = blocks.last_mut() {
+                    block.close = i;
                 }
-            }
 
-            if is_found_key && line.find('}').is_some() {
-                break;
+                is_key = false;
+                continue;
             }
 
-            if is_found_key {
-                let fmt_line = line.trim();
-                var_val.push_str(&format!("{},", fmt_line));
+            if is_key
+                && !fmt_line.is_empty()
+                && let Some(block) = blocks.last_mut()
+            {
+                let (var, val) = fmt_line.split_once('=').unwrap_or((fmt_line, ""));
+                block.entries.push((var.to_string(), val.to_string(), i));
             }
         }
 
-        let old_var: Vec<&str > = var_val.split(',').filter(|s| !s.is_empty()).collect();
-        
-        for v in old_var {
-            let v_v = v.split('=').next().unwrap_or(v);
+        if self.duplicates == DuplicatePolicy::Error {
+            for (i, block) in blocks.iter().enumerate() {
+                if blocks[..i].iter().any(|b| b.key == block.key) {
+                    return Err(Error::KeyExists(block.key.clone()));
+                }
+            }
 
-            if v_v == var.trim() {
-                is_varible = true;
-                break;
+            for block in &blocks {
+                for (j, (var, _, _)) in block.entries.iter().enumerate() {
+                    if block.entries[..j].iter().any(|(v, _, _)| v == var) {
+                        return Err(Error::VarExists { key: block.key.clone(), var: var.clone() });
+                    }
+                }
             }
         }
 
-        Ok(is_varible)
-
+        Ok(blocks)
     }
 
-    /// Добавляет новую переменную с указанным значением в секцию ключа.
-    /// Если ключа нет, он будет создан. Если переменная уже существует, операция не выполнится.
+    /// Получает значение переменной по ключу и имени переменной.
+    /// Ссылки `${section.var}` и `${env:NAME}` в значении подставляются, `$${` даёт буквальное `${`.
+    /// Если переменная не найдена, возвращает "NONE_VAL".
+    /// Ошибка подстановки (цикл или неизвестная ссылка) возвращается как `InvalidData` с `Error` внутри.
+    /// Читается только этот файл: директивы `@include` пропускаются, для них есть `compose`.
     ///
     /// # Аргументы
     /// * `key` - Имя секции (ключа)
     /// * `var` - Имя переменной
-    /// * `val` - Значение переменной
     ///
-    /// Adds a new variable with the specified value to the key section.
-    /// If the key does not exist, it will be created. If the variable already exists, the operation will not be performed.
+    /// Gets the value of a variable by key and variable name.
+    /// `${section.var}` and `${env:NAME}` references in the value are substituted, `$${` gives a literal `${`.
+    /// If the variable is not found, returns "NONE_VAL".
+    /// A substitution error (a cycle or an unknown reference) is returned as `InvalidData` wrapping an `Error`.
+    /// Only this file is read: `@include` directives are skipped, use `compose` to follow them.
     ///
     /// # Arguments
     /// * `key` - Section (key) name
     /// * `var` - Variable name
-    /// * `val` - Variable value
-    pub fn add<T: std::fmt::Display, V: std::fmt::Display>(&self, key: &str, var: T, val: V) -> std::io::Result<()> {
+    pub fn get<T: std::fmt::Display>(&self, key: &str, var: T) -> std::io::Result<String> {
         let var_str = var.to_string();
-        let val_str = val.to_string();
-
-        if !self.is_ccf()? {
-            println!("File integrity error!");
-            return Ok(());
-        }
-        
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let mut is_found = false;
-        let mut is_not_key = true;
-        let mut new_text: String = String::new();
-
-        for line in reader.lines() {
-            let line = line?;
-            
-            if line.find('(').is_some() {
-                let cleaned_line = line.replace('(', "").replace(')', "").replace('{', "");
-                let k = cleaned_line.trim();
-                
-                if k == key {
-                    is_found = true;
-                    is_not_key = false;
-                }
-            }
+        let raw = self.get_raw(key, var_str.as_str())?;
 
-            if line.find('}').is_some() && is_found {
-                let is_val = self.is_var(key, var_str.as_str())?;
-
-                if is_val {
-                    println!("A variable named '{}' already exists!", var_str);
-                    return Ok(());
-                } 
-
-                new_text.push_str(&format!("\t{}={}\n", var_str, val_str));
-                is_found = false;
-            }
-
-            new_text.push_str(&line);
-            new_text.push_str("\n");
-            
+        if !raw.contains('$') || !self.is_ccf()? {
+            return Ok(raw);
         }
 
-        if is_not_key {
-            new_text.push_str(&format!("({}) {{\n\t{}={}\n}}", key, var_str, val_str));
-        }
-       
-
-        let mut file = fs::OpenOptions::new().write(true).truncate(true).create(true).open(&self.filename)?;
-        write!(file, "{}", new_text)?;
+        let doc = self.load()?;
 
-        Ok(())
+        Ok(interpolate::resolve_value(&doc, key, &var_str, &raw)?)
     }
 
-    /// Получает значение переменной по ключу и имени переменной.
+    /// Получает исходное значение переменной без подстановки ссылок.
     /// Если переменная не найдена, возвращает "NONE_VAL".
     ///
     /// # Аргументы
     /// * `key` - Имя секции (ключа)
     /// * `var` - Имя переменной
     ///
-    /// Gets the value of a variable by key and variable name.
+    /// Gets the raw value of a variable without substituting references.
     /// If the variable is not found, returns "NONE_VAL".
     ///
     /// # Arguments
     /// * `key` - Section (key) name
     /// * `var` - Variable name
-    pub fn get<T: std::fmt::Display>(&self, key: &str, var: T) -> std::io::Result<String> {
+    pub fn get_raw<T: std::fmt::Display>(&self, key: &str, var: T) -> std::io::Result<String> {
         let var_str = var.to_string();
         
         if !self.is_ccf()? {
@@ -249,43 +344,15 @@ impl TNT {
             return Ok("Error".to_string());
         }
 
-        let file = fs::File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let mut is_key = false;
+        let blocks = self.read_blocks()?;
         let mut fmt_val = String::new();
 
-
-        for line in reader.lines() {
-            let line = line?;
-
-            if line.find('(').is_some() {
-                let cleaned_line = line.replace(['(', ')', '{'], "");
-                let k = cleaned_line.trim();
-                
-                if k == key {
-                    is_key = true;
-                    continue;
-                }
-            }
-
-            if is_key && line.find('}').is_some() {
-                println!("The variable was not found!");
-                break;
-            }
-
-
-            if is_key {
-                if let Some((name, val)) = line.trim().split_once("=") {
-                    if name == var_str.trim() {
-                        fmt_val.push_str(val);
-                        break;
-                    }
-                }
-            }
-
+        match self.find_var(&blocks, key, &var_str) {
+            Some((_, val)) => fmt_val.push_str(secret::plain(&val)),
+            None if !self.scope(&blocks, key).is_empty() => println!("The variable was not found!"),
+            None => {}
         }
 
-
         if fmt_val.is_empty() {
             fmt_val.push_str("NONE_VAL");
         }
@@ -294,352 +361,1062 @@ impl TNT {
 
     }
 
-    /// Изменяет значение переменной в секции ключа.
-    /// Если переменная не найдена, операция не выполнится.
+    /// Получает значение переменной и расшифровывает его, если оно зашифровано.
     ///
     /// # Аргументы
     /// * `key` - Имя секции (ключа)
     /// * `var` - Имя переменной
-    /// * `new_val` - Новое значение переменной
+    /// * `secret_key` - Ключ шифрования
     ///
-    /// Edits the value of a variable in the key section.
-    /// If the variable is not found, the operation will not be performed.
+    /// Gets the value of a variable and decrypts it if it is encrypted.
     ///
     /// # Arguments
     /// * `key` - Section (key) name
     /// * `var` - Variable name
-    /// * `new_val` - New value for the variable
-    pub fn edit<T: std::fmt::Display, V: std::fmt::Display>(&self, key: &str, var: T, new_val: V) -> std::io::Result<()> {
-        let var_str = var.to_string();
-        let val_str = new_val.to_string();
-
-        if !self.is_ccf()? {
-            println!("File integrity error!");
-            return Ok(());
-        }
-
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let pos = self.get_var_line(key, var_str.as_str())?;
-        let mut txt = String::new();
-
-        if pos == -1 {
-            println!("The variable was not found!");
-            return Ok(());
-        }
-
-        for (i, line) in reader.lines().enumerate() {
-            let line = line?;
+    /// * `secret_key` - Encryption key
+    #[cfg(feature = "encryption")]
+    pub fn get_decrypted(&self, key: &str, var: &str, secret_key: &secret::SecretKey) -> Result<Option<String>, Error> {
+        let doc = self.load()?;
 
-            if i as i32 == pos {
-                txt.push_str(&format!("\t{}={}\n", var_str, val_str));
-                continue;
-            }
-
-            txt.push_str(&line);
-            txt.push_str("\n");
-        }
-
-        let mut file = fs::OpenOptions::new().write(true).truncate(true).create(true).open(&self.filename)?;
-        write!(file, "{}", txt)?;
-
-        Ok(())
+        doc.get(key, var).map(|val| secret_key.decrypt(val)).transpose()
     }
 
-    /// Удаляет переменную из секции ключа.
-    /// Если переменная не найдена, операция не выполнится.
+    /// Получает все значения переменных в секции ключа.
     ///
     /// # Аргументы
     /// * `key` - Имя секции (ключа)
-    /// * `var` - Имя переменной
     ///
-    /// Deletes a variable from the key section.
-    /// If the variable is not found, the operation will not be performed.
+    /// Gets all variable values in the key section.
     ///
     /// # Arguments
     /// * `key` - Section (key) name
-    /// * `var` - Variable name
-    pub fn delete_var<T: std::fmt::Display>(&self, key: &str, var: T) -> std::io::Result<()> {
-        let var_str = var.to_string();
+    pub fn get_all(&self, key: &str) -> std::io::Result<Vec<String>> {
 
         if !self.is_ccf()? {
             println!("File integrity error!");
-            return Ok(());
+            return Ok(vec!["".to_string()]);
         }
 
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let mut txt = String::new();
-        let var_del_line = self.get_var_line(key, var_str.as_str())?;
+        let doc = self.load()?;
 
-        if var_del_line == -1 {
-            println!("The variable was not found!");
-            return Ok(());
-        }
+        Ok(doc.entries(key).iter().map(|(_, val)| val.clone()).collect())
 
-        for (i, line) in reader.lines().enumerate() {
-            let line = line?;
+    }
 
-            if i == var_del_line as usize {
-                continue;
-            }
+    /// Получает имена всех секций (ключей) в порядке их следования в файле.
+    ///
+    /// Gets the names of all sections (keys) in file order.
+    ///
+    /// # Errors
+    /// `Error::Integrity` if the file fails the integrity check.
+    pub fn sections(&self) -> Result<Vec<String>, Error> {
+        let doc = self.load()?;
 
-            txt.push_str(&line);
-            txt.push_str("\n");
-        }
+        Ok(doc.sections().iter().map(|s| s.name().to_string()).collect())
+    }
 
-        let mut file = fs::OpenOptions::new().write(true).truncate(true).create(true).open(&self.filename)?;
-        write!(file, "{}", txt)?;
+    /// Получает имена всех переменных в секции ключа.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    ///
+    /// Gets the names of all variables in the key section.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    ///
+    /// # Errors
+    /// `Error::Integrity` if the file fails the integrity check.
+    pub fn vars(&self, key: &str) -> Result<Vec<String>, Error> {
+        let entries = self.entries(key)?;
 
-        Ok(())            
+        Ok(entries.into_iter().map(|(var, _)| var).collect())
     }
 
-    /// Удаляет секцию (ключ) и все переменные внутри неё.
-    /// Если ключ не найден, операция не выполнится.
+    /// Получает все пары (переменная, значение) в секции ключа.
     ///
     /// # Аргументы
     /// * `key` - Имя секции (ключа)
     ///
-    /// Deletes a section (key) and all variables inside it.
-    /// If the key is not found, the operation will not be performed.
+    /// Gets all (variable, value) pairs in the key section.
     ///
     /// # Arguments
     /// * `key` - Section (key) name
-    pub fn delete_key(&self, key: &str) -> std::io::Result<()> {
+    ///
+    /// # Errors
+    /// `Error::Integrity` if the file fails the integrity check.
+    pub fn entries(&self, key: &str) -> Result<Vec<(String, String)>, Error> {
+        let doc = self.load()?;
 
-        if !self.is_ccf()? {
-            println!("File integrity error!");
-            return Ok(());
+        Ok(doc.entries(key).to_vec())
+    }
+
+    /// Возвращает итератор по всем тройкам (секция, переменная, значение) в порядке их следования в файле.
+    ///
+    /// Returns an iterator over all (section, variable, value) triples in file order.
+    ///
+    /// # Errors
+    /// `Error::Integrity` if the file fails the integrity check.
+    pub fn iter(&self) -> Result<std::vec::IntoIter<(String, String, String)>, Error> {
+        let doc = self.load()?;
+        let mut all: Vec<(String, String, String)> = Vec::new();
+
+        for section in doc.sections() {
+            for (var, val) in section.entries() {
+                all.push((section.name().to_string(), var.clone(), val.clone()));
+            }
         }
 
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let mut txt = String::new();
-        let mut is_found_key = false;
-        let mut is_key = false;
+        Ok(all.into_iter())
+    }
 
-        for line in reader.lines() {
-            let line = line?;
+    /// Загружает весь файл в документ в памяти. Повторы обрабатываются по правилу `duplicates`.
+    /// Документ запоминает время изменения и хеш файла, чтобы `save` мог обнаружить чужую правку.
+    /// Директивы `@include` не раскрываются (см. `compose`).
+    ///
+    /// Loads the whole file into an in-memory document. Repeats are handled by the `duplicates` policy.
+    /// The document remembers the modification time and hash of the file so that `save` can detect a foreign edit.
+    /// `@include` directives are not followed (see `compose`).
+    pub fn load(&self) -> Result<Document, Error> {
+        let stamp = self.storage.stamp()?;
+        let txt = self.storage.read()?;
+
+        let mut doc = Document::parse(&txt)?;
+        doc.dedup(self.duplicates)?;
+        doc.set_origin(Origin::new(stamp, &txt));
+
+        Ok(doc)
+    }
 
-            if line.find('(').is_some() {
-                let cleaned_line = line.replace(['(', ')', '{'], "");
-                let k = cleaned_line.trim();
+    /// Проверяет, что хранилище не изменилось с момента загрузки документа.
+    fn check_origin(&self, doc: &Document) -> Result<(), Error> {
+        let Some(origin) = doc.origin() else {
+            return Ok(());
+        };
 
-                if k == key.trim() {
-                    is_key = true;
-                    is_found_key = true;
-                    continue;
-                }
-            }
+        let stamp = self.storage.stamp()?;
 
-            if is_key && line.find('}').is_some() {
-                is_key = false;
-                continue;
-            }
+        if stamp.is_some() && stamp == origin.stamp {
+            return Ok(());
+        }
 
-            if is_key {
-                continue;
-            }
+        let txt = self.storage.read()?;
 
-            txt.push_str(&line);
-            txt.push_str("\n");
+        if Origin::hash(&txt) == origin.hash {
+            return Ok(());
         }
 
-        if is_found_key {
-            let mut file = fs::OpenOptions::new().write(true).truncate(true).create(true).open(&self.filename)?;
-            write!(file, "{}", txt)?;
-        }
+        let mut loaded = Document::parse(&origin.text)?;
+        loaded.dedup(self.duplicates)?;
+        let mut current = Document::parse(&txt)?;
+        current.dedup(self.duplicates)?;
 
-        Ok(())
+        Err(Error::Conflict(diff::diff(&loaded, &current)))
     }
 
-    /// Очищает весь файл, удаляя все данные.
+    /// Проверяет файл: баланс скобок и, при правиле `DuplicatePolicy::Error`, отсутствие повторов.
     ///
-    /// Clears the entire file, removing all data.
-    pub fn clear(&self) -> std::io::Result<()> {
-        File::create(&self.filename)?;
+    /// Validates the file: balanced brackets and, under `DuplicatePolicy::Error`, no repeats.
+    ///
+    /// # Errors
+    /// `Error::Integrity`, `Error::KeyExists` or `Error::VarExists`.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.load()?;
+
         Ok(())
     }
 
-    /// Получает все значения переменных в секции ключа.
+    /// Экспортирует данные в формате TOML в указанный файл.
+    /// Секция профиля записывается с ключом в кавычках: `["db @prod"]`.
     ///
     /// # Аргументы
-    /// * `key` - Имя секции (ключа)
+    /// * `filename` - Имя файла (без расширения), куда будет сохранён TOML.
     ///
-    /// Gets all variable values in the key section.
+    /// Exports data in TOML format to the specified file.
+    /// A profile section is written with a quoted key: `["db @prod"]`.
     ///
     /// # Arguments
-    /// * `key` - Section (key) name
-    pub fn get_all(&self, key: &str) -> std::io::Result<Vec<String>> {
-
+    /// * `filename` - File name (without extension) where TOML will be saved.
+    pub fn to_toml(&self, filename: &str) -> std::io::Result<()> {
+
         if !self.is_ccf()? {
             println!("File integrity error!");
-            return Ok(vec!["".to_string()]);
+            return Ok(());
         }
 
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let mut all_val: Vec<String> = Vec::new();
-        let mut is_key = false;
+        let doc = self.load()?;
 
-        for line in reader.lines() {
-            let line = line?;
+        export_toml(&doc, filename, false)
+    }
 
-            if line.find('(').is_some() {
-                let cleaned_line = line.replace(['(', ')', '{'], "");
-                let k = cleaned_line.trim();
+    /// Экспортирует данные в формате TOML, не скрывая секретные значения.
+    ///
+    /// # Аргументы
+    /// * `filename` - Имя файла (без расширения), куда будет сохранён TOML.
+    ///
+    /// Exports data in TOML format without redacting secret values.
+    ///
+    /// # Arguments
+    /// * `filename` - File name (without extension) where TOML will be saved.
+    pub fn to_toml_revealed(&self, filename: &str) -> std::io::Result<()> {
+        let doc = self.load()?;
 
-                if k == key.trim() {
-                    is_key = true;
-                    continue;
+        export_toml(&doc, filename, true)
+    }
+
+    /// Экспортирует данные в формате TOML, подставив все ссылки `${...}`.
+    ///
+    /// # Аргументы
+    /// * `filename` - Имя файла (без расширения), куда будет сохранён TOML.
+    ///
+    /// Exports data in TOML format with every `${...}` reference substituted.
+    ///
+    /// # Arguments
+    /// * `filename` - File name (without extension) where TOML will be saved.
+    pub fn to_toml_resolved(&self, filename: &str) -> std::io::Result<()> {
+        let doc = self.load()?.resolved()?;
+
+        export_toml(&doc, filename, false)
+    }
+
+    /// Экспортирует данные в формате JSON в указанный файл.
+    ///
+    /// # Аргументы
+    /// * `filename` - Имя файла (без расширения), куда будет сохранён JSON.
+    ///
+    /// Exports data in JSON format to the specified file.
+    ///
+    /// # Arguments
+    /// * `filename` - File name (without extension) where JSON will be saved.
+    pub fn to_json(&self, filename: &str) -> std::io::Result<()> {
+
+        if !self.is_ccf()? {
+            println!("File integrity error!");
+            return Ok(());
+        }
+
+        let doc = self.load()?;
+
+        export_json(&doc, filename, false)
+    }
+
+    /// Экспортирует данные в формате JSON, не скрывая секретные значения.
+    ///
+    /// # Аргументы
+    /// * `filename` - Имя файла (без расширения), куда будет сохранён JSON.
+    ///
+    /// Exports data in JSON format without redacting secret values.
+    ///
+    /// # Arguments
+    /// * `filename` - File name (without extension) where JSON will be saved.
+    pub fn to_json_revealed(&self, filename: &str) -> std::io::Result<()> {
+        let doc = self.load()?;
+
+        export_json(&doc, filename, true)
+    }
+
+    /// Экспортирует данные в формате JSON, подставив все ссылки `${...}`.
+    ///
+    /// # Аргументы
+    /// * `filename` - Имя файла (без расширения), куда будет сохранён JSON.
+    ///
+    /// Exports data in JSON format with every `${...}` reference substituted.
+    ///
+    /// # Arguments
+    /// * `filename` - File name (without extension) where JSON will be saved.
+    pub fn to_json_resolved(&self, filename: &str) -> std::io::Result<()> {
+        let doc = self.load()?.resolved()?;
+
+        export_json(&doc, filename, false)
+    }
+}
+
+impl<S: WritableStorage> TNT<S> {
+    /// Включает журнал аудита: каждая изменяющая операция отправляет в `sink` по записи
+    /// на каждую изменённую переменную (время, операция, секция, переменная, прежнее и новое значение, `actor`).
+    /// Значения секретных переменных вместо открытого текста записываются как хеш SHA-256.
+    ///
+    /// Записи отправляются до записи в хранилище. Если `sink` вернул ошибку, изменение не сохраняется
+    /// и операция возвращает эту ошибку; если не удалась сама запись в хранилище, в журнале аудита
+    /// остаются записи о несохранённом изменении. Аудит наследуют `Composed` из `compose`;
+    /// у `AsyncTnt` он включается отдельно (`AsyncTnt::audit`).
+    ///
+    /// Хеш не солёный: короткий или предсказуемый секрет (PIN, словарный пароль) восстанавливается
+    /// по нему перебором. Журнал аудита нужно защищать так же, как сам файл.
+    ///
+    /// # Аргументы
+    /// * `sink` - Приёмник записей (см. `AuditSink`, `JsonLinesSink`)
+    /// * `actor` - Кто вносит изменения через этот экземпляр
+    ///
+    /// # Пример
+    /// ```no_run
+    /// # use tnt::{JsonLinesSink, TNT};
+    /// let tnt = TNT::open("data.txt").unwrap().audit(JsonLinesSink::open("audit.jsonl").unwrap(), "alice");
+    /// ```
+    ///
+    /// Enables the audit log: every mutating operation sends `sink` one record
+    /// per changed variable (time, operation, section, variable, old and new value, `actor`).
+    /// Values of secret variables are recorded as a SHA-256 hash instead of plain text.
+    ///
+    /// Records are sent before the storage is written. If `sink` returns an error, the change is not saved
+    /// and the operation returns that error; if the storage write itself fails, the audit log keeps
+    /// records of a change that was not saved. `Composed` from `compose` inherits the audit;
+    /// `AsyncTnt` enables it separately (`AsyncTnt::audit`).
+    ///
+    /// The hash is unsalted: a short or predictable secret (a PIN, a dictionary password) can be recovered
+    /// from it by brute force. Protect the audit log the same way as the file itself.
+    ///
+    /// # Arguments
+    /// * `sink` - Record sink (see `AuditSink`, `JsonLinesSink`)
+    /// * `actor` - Who makes changes through this instance
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use tnt::{JsonLinesSink, TNT};
+    /// let tnt = TNT::open("data.txt").unwrap().audit(JsonLinesSink::open("audit.jsonl").unwrap(), "alice");
+    /// ```
+    pub fn audit(mut self, sink: impl AuditSink + 'static, actor: &str) -> Self {
+        self.audit = Some(Auditor::new(Arc::new(sink), actor));
+        self
+    }
+
+    /// Записывает текст в хранилище. Если включён аудит, сначала отправляет записи об изменениях
+    /// и при ошибке приёмника ничего не записывает. Вызывается под `locked`.
+    pub(crate) fn commit(&self, operation: &str, txt: &str) -> std::io::Result<()> {
+        let Some(audit) = &self.audit else {
+            return self.storage.write(txt);
+        };
+
+        let before = match self.storage.read() {
+            Ok(before) => before,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        if before != txt {
+            audit.record(operation, &before, txt)?;
+        }
+
+        self.storage.write(txt)
+    }
+
+    /// Добавляет новую переменную с указанным значением в секцию ключа.
+    /// Если ключа нет, он будет создан. Если переменная уже существует, операция не выполнится.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
+    ///
+    /// Adds a new variable with the specified value to the key section.
+    /// If the key does not exist, it will be created. If the variable already exists, the operation will not be performed.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    pub fn add<T: std::fmt::Display, V: std::fmt::Display>(&self, key: &str, var: T, val: V) -> std::io::Result<()> {
+        self.locked(|| {
+            let var_str = var.to_string();
+            let val_str = val.to_string();
+
+            if !self.is_ccf()? {
+                println!("File integrity error!");
+                return Ok(());
+            }
+
+            let blocks = self.read_blocks()?;
+
+            if self.find_var(&blocks, key, &var_str).is_some() {
+                println!("A variable named '{}' already exists!", var_str);
+                return Ok(());
+            }
+
+            let source = self.storage.read()?;
+            let close = self.target_block(&blocks, key).map(|b| b.close);
+            let mut new_text: String = String::new();
+
+            for (i, line) in source.lines().enumerate() {
+
+                if Some(i) == close {
+                    new_text.push_str(&format!("\t{}={}\n", var_str, val_str));
                 }
+
+                new_text.push_str(line);
+                new_text.push('\n');
             }
 
-            if is_key && line.find('}').is_some() {
-                break;
+            if close.is_none() {
+                new_text.push_str(&format!("({}) {{\n\t{}={}\n}}", key, var_str, val_str));
             }
 
-            if is_key {
-                let fmt_line = line.split('=').nth(1).unwrap_or("");
-                let val_fmt = fmt_line.trim().to_string();
-                all_val.push(val_fmt);
+            self.commit("add", &new_text)?;
+
+            Ok(())
+        })
+    }
+
+    /// Изменяет значение переменной в секции ключа.
+    /// Если переменная не найдена, операция не выполнится.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `new_val` - Новое значение переменной
+    ///
+    /// Edits the value of a variable in the key section.
+    /// If the variable is not found, the operation will not be performed.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `new_val` - New value for the variable
+    pub fn edit<T: std::fmt::Display, V: std::fmt::Display>(&self, key: &str, var: T, new_val: V) -> std::io::Result<()> {
+        self.locked(|| {
+            let var_str = var.to_string();
+            let val_str = new_val.to_string();
+
+            if !self.is_ccf()? {
+                println!("File integrity error!");
+                return Ok(());
             }
-        }
 
-        Ok(all_val)
+            let source = self.storage.read()?;
+            let pos = self.get_var_line(key, var_str.as_str())?;
+            let mut txt = String::new();
 
+            if pos == -1 {
+                println!("The variable was not found!");
+                return Ok(());
+            }
+
+            for (i, line) in source.lines().enumerate() {
+
+                if i as i32 == pos {
+                    txt.push_str(&entry_line(line, &var_str, &val_str));
+                    continue;
+                }
+
+                txt.push_str(line);
+                txt.push('\n');
+            }
+
+            self.commit("edit", &txt)?;
+
+            Ok(())
+        })
     }
 
-    /// Экспортирует данные в формате TOML в указанный файл.
+    /// Устанавливает значение переменной в секции ключа.
+    /// Если ключа или переменной нет, они будут созданы, иначе значение будет заменено.
     ///
     /// # Аргументы
-    /// * `filename` - Имя файла (без расширения), куда будет сохранён TOML.
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
     ///
-    /// Exports data in TOML format to the specified file.
+    /// Sets the value of a variable in the key section.
+    /// If the key or the variable does not exist, it will be created, otherwise the value is replaced.
     ///
     /// # Arguments
-    /// * `filename` - File name (without extension) where TOML will be saved.
-    pub fn to_toml(&self, filename: &str) -> std::io::Result<()> {
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    ///
+    /// # Errors
+    /// `Error::Integrity` if the file fails the integrity check, `Error::Parse` if a name or the value
+    /// would break the markup (see `Document::set`). Nothing is written then.
+    pub fn set<T: std::fmt::Display, V: std::fmt::Display>(&self, key: &str, var: T, val: V) -> Result<(), Error> {
+        self.set_many(&[(key, var, val)])
+    }
+
+    /// Устанавливает значения нескольких переменных за одну запись файла.
+    /// Каждая тройка обрабатывается так же, как в `set`; при повторе пары (ключ, переменная) побеждает последнее значение.
+    ///
+    /// # Аргументы
+    /// * `items` - Тройки (ключ, переменная, значение)
+    ///
+    /// Sets the values of several variables in a single file write.
+    /// Each triple is handled like `set`; if a (key, variable) pair repeats, the last value wins.
+    ///
+    /// # Arguments
+    /// * `items` - (key, variable, value) triples
+    ///
+    /// # Errors
+    /// As in `set`; if any triple is rejected, nothing is written.
+    pub fn set_many<T: std::fmt::Display, V: std::fmt::Display>(&self, items: &[(&str, T, V)]) -> Result<(), Error> {
+        self.locked(|| self.upsert(items))
+    }
+
+    /// Общая часть `set_many`, `compare_and_set`, `increment` и операций со списками. Вызывается под `locked`.
+    pub(crate) fn upsert<T: std::fmt::Display, V: std::fmt::Display>(&self, items: &[(&str, T, V)]) -> Result<(), Error> {
 
         if !self.is_ccf()? {
-            println!("File integrity error!");
-            return Ok(());
+            return Err(Error::Integrity);
         }
 
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let mut is_key = false;
-        let mut txt: String = String::new();
+        let mut pending: Vec<(String, String, String)> = Vec::new();
 
-        for line in reader.lines() {
-            let line = line?;
+        for (key, var, val) in items {
+            let key_str = key.trim().to_string();
+            let var_str = var.to_string().trim().to_string();
+            let val_str = val.to_string();
+            document::check_key(&key_str)?;
+            document::check_var(&var_str)?;
+            document::check_val(&val_str)?;
 
-            if line.find('(').is_some() {
-                let cleaned_line = line.replace(['(', ')', '{'], "");
-                let key = cleaned_line.trim();
-                txt.push_str(&format!("[{}]\n", key));
-                is_key = true;
-                continue;
+            match pending.iter_mut().find(|(k, v, _)| *k == key_str && *v == var_str) {
+                Some(item) => item.2 = val_str,
+                None => pending.push((key_str, var_str, val_str)),
             }
+        }
 
-            if is_key && line.find('}').is_some() {
-                is_key = false;
-                txt.push_str("\n");
-                continue;
+        let blocks = self.read_blocks()?;
+        let mut replaced: Vec<(usize, &str, &str)> = Vec::new();
+        let mut inserted: Vec<(usize, &str, &str)> = Vec::new();
+        let mut new_keys: Vec<(&str, Vec<(&str, &str)>)> = Vec::new();
+
+        for (key, var, val) in &pending {
+            if let Some((pos, _)) = self.find_var(&blocks, key, var) {
+                replaced.push((pos, var, val));
+            } else if let Some(block) = self.target_block(&blocks, key) {
+                inserted.push((block.close, var, val));
+            } else {
+                match new_keys.iter_mut().find(|(k, _)| k == key) {
+                    Some((_, entries)) => entries.push((var, val)),
+                    None => new_keys.push((key, vec![(var, val)])),
+                }
+            }
+        }
+
+        let source = self.storage.read()?;
+        let mut txt = String::new();
+
+        for (i, line) in source.lines().enumerate() {
+
+            for (_, var, val) in inserted.iter().filter(|(close, _, _)| *close == i) {
+                txt.push_str(&format!("\t{}={}\n", var, val));
+            }
+
+            match replaced.iter().find(|(pos, _, _)| *pos == i) {
+                Some((_, var, val)) => txt.push_str(&entry_line(line, var, val)),
+                None => {
+                    txt.push_str(line);
+                    txt.push('\n');
+                }
             }
+        }
+
+        for (key, entries) in new_keys {
+            txt.push_str(&format!("({}) {{\n", key));
 
-            if is_key {
-                let fmt_val = line.trim();
-                let var = fmt_val.split('=').next().unwrap_or(fmt_val);
-                let val = fmt_val.split('=').nth(1).unwrap_or("");
-                txt.push_str(&format!("{}=\"{}\"\n", var, val));
+            for (var, val) in entries {
+                txt.push_str(&format!("\t{}={}\n", var, val));
             }
 
+            txt.push_str("}\n");
         }
 
-        let full_filename = String::from(&format!("{}.toml", filename));
-        let mut file = fs::OpenOptions::new().write(true).truncate(true).create(true).open(full_filename)?;
-        write!(file, "{}", txt)?;
-        
+        self.commit("set", &txt)?;
+
         Ok(())
     }
 
-    /// Экспортирует данные в формате JSON в указанный файл.
+    /// Устанавливает секретное значение: переменная записывается с маркером `!secret`
+    /// и скрывается в `Debug`, `diff` и экспорте.
     ///
     /// # Аргументы
-    /// * `filename` - Имя файла (без расширения), куда будет сохранён JSON.
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
     ///
-    /// Exports data in JSON format to the specified file.
+    /// Sets a secret value: the variable is written with the `!secret` marker
+    /// and redacted in `Debug`, `diff` and exports.
     ///
     /// # Arguments
-    /// * `filename` - File name (without extension) where JSON will be saved.
-    pub fn to_json(&self, filename: &str) -> std::io::Result<()> {
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    ///
+    /// # Errors
+    /// As in `set`.
+    pub fn set_secret<T: std::fmt::Display, V: std::fmt::Display>(&self, key: &str, var: T, val: V) -> Result<(), Error> {
+        self.set(key, var, format!("{} {}", secret::MARKER, val))
+    }
 
-        if !self.is_ccf()? {
-            println!("File integrity error!");
-            return Ok(());
-        }
+    /// Шифрует значение ключом и записывает его как `!secret enc:<hex>`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `val` - Значение переменной
+    /// * `secret_key` - Ключ шифрования
+    ///
+    /// Encrypts a value with the key and writes it as `!secret enc:<hex>`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `val` - Variable value
+    /// * `secret_key` - Encryption key
+    #[cfg(feature = "encryption")]
+    pub fn set_encrypted<T: std::fmt::Display>(&self, key: &str, var: T, val: &str, secret_key: &secret::SecretKey) -> Result<(), Error> {
+        self.set_secret(key, var, secret_key.encrypt(val)?)?;
 
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(&file);
-        let mut txt: String = String::new();
-        let mut is_key: bool = false;
-        let mut val_str = String::new();
+        Ok(())
+    }
+
+    /// Удаляет переменную из секции ключа.
+    /// Если переменная не найдена, операция не выполнится.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    ///
+    /// Deletes a variable from the key section.
+    /// If the variable is not found, the operation will not be performed.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    pub fn delete_var<T: std::fmt::Display>(&self, key: &str, var: T) -> std::io::Result<()> {
+        self.locked(|| {
+            let var_str = var.to_string();
 
-        txt.push_str("{\n");
+            if !self.is_ccf()? {
+                println!("File integrity error!");
+                return Ok(());
+            }
 
-        for line in reader.lines() {
-            let line = line?;
+            let source = self.storage.read()?;
+            let mut txt = String::new();
+            let blocks = self.read_blocks()?;
+            let var_del_lines: Vec<usize> = self.var_lines(&blocks, key, &var_str).into_iter().map(|(i, _)| i).collect();
 
-            if line.find('(').is_some() {
-                let cleaned_line = line.replace(['(', ')', '{'], "");
-                let key = cleaned_line.trim();
-                txt.push_str(&format!("\t\"{}\": {{\n", key));
-                is_key = true;
-                continue;
+            if var_del_lines.is_empty() {
+                println!("The variable was not found!");
+                return Ok(());
             }
 
-            if is_key && line.find('}').is_some() {
-                is_key = false;
-                let mut new_len = val_str.len() - 2;
+            for (i, line) in source.lines().enumerate() {
 
-                while !val_str.is_char_boundary(new_len) && new_len > 0 {
-                    new_len -= 1;
+                if var_del_lines.contains(&i) {
+                    continue;
                 }
 
-                val_str.truncate(new_len);
+                txt.push_str(line);
+                txt.push('\n');
+            }
+
+            self.commit("delete_var", &txt)?;
 
-                txt.push_str(&val_str);
-                txt.push_str("\n\t},\n\n");
-                val_str.clear();
-                continue;
+            Ok(())            
+        })
+    }
+
+    /// Удаляет секцию (ключ) и все переменные внутри неё.
+    /// Если ключ не найден, операция не выполнится.
+    /// При `FirstWins` и `LastWins` удаляется только действующий блок, и становится виден его повтор;
+    /// при `Merge` и `Error` удаляются все блоки секции.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    ///
+    /// Deletes a section (key) and all variables inside it.
+    /// If the key is not found, the operation will not be performed.
+    /// Under `FirstWins` and `LastWins` only the effective block is deleted, and its repeat becomes visible;
+    /// under `Merge` and `Error` every block of the section is deleted.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    pub fn delete_key(&self, key: &str) -> std::io::Result<()> {
+        self.locked(|| {
+            if !self.is_ccf()? {
+                println!("File integrity error!");
+                return Ok(());
             }
 
-            if is_key {
-                let fmt_line = line.trim();
-                let var = fmt_line.split('=').next().unwrap_or(fmt_line);
-                let val = fmt_line.split('=').nth(1).unwrap_or("");
-                val_str.push_str(&format!("\t\t\"{}\": \"{}\",\n", val, var));
+            let blocks = self.read_blocks()?;
+            let scope = self.scope(&blocks, key);
+
+            if scope.is_empty() {
+                return Ok(());
             }
 
-        }
+            let source = self.storage.read()?;
+            let mut txt = String::new();
 
-        let mut new_len = txt.len() - 3;
+            for (i, line) in source.lines().enumerate() {
 
-        while !txt.is_char_boundary(new_len) && new_len > 0 {
-            new_len -= 1;
-        }
+                if scope.iter().any(|b| (b.open..=b.close).contains(&i)) {
+                    continue;
+                }
+
+                txt.push_str(line);
+                txt.push('\n');
+            }
+
+            self.commit("delete_key", &txt)?;
+
+            Ok(())
+        })
+    }
+
+    /// Переименовывает секцию (ключ), сохраняя её положение и содержимое.
+    ///
+    /// # Аргументы
+    /// * `old` - Текущее имя секции
+    /// * `new` - Новое имя секции
+    ///
+    /// Renames a section (key), keeping its position and contents.
+    ///
+    /// # Arguments
+    /// * `old` - Current section name
+    /// * `new` - New section name
+    ///
+    /// # Errors
+    /// `Error::KeyNotFound` if `old` does not exist, `Error::KeyExists` if `new` is already taken.
+    pub fn rename_section(&self, old: &str, new: &str) -> Result<(), Error> {
+        self.locked(|| {
+            if !self.is_ccf()? {
+                return Err(Error::Integrity);
+            }
+
+            let blocks = self.read_blocks()?;
+
+            if !blocks.iter().any(|b| b.key == old.trim()) {
+                return Err(Error::KeyNotFound(old.trim().to_string()));
+            }
+
+            if old.trim() == new.trim() {
+                return Ok(());
+            }
+
+            if blocks.iter().any(|b| b.key == new.trim()) {
+                return Err(Error::KeyExists(new.trim().to_string()));
+            }
+
+            let source = self.storage.read()?;
+            let mut txt = String::new();
+            let mut is_key = false;
+
+            for line in source.lines() {
+
+                if !is_key && line.contains('(') {
+                    let cleaned_line = line.replace(['(', ')', '{'], "");
+                    is_key = true;
+
+                    if cleaned_line.trim() == old.trim() {
+                        txt.push_str(&format!("({}) {{\n", new.trim()));
+                        continue;
+                    }
+                }
+
+                if is_key && line.trim_start().starts_with('}') {
+                    is_key = false;
+                }
+
+                txt.push_str(line);
+                txt.push('\n');
+            }
+
+            self.commit("rename_section", &txt)?;
+
+            Ok(())
+        })
+    }
+
+    /// Переименовывает переменную в секции ключа, сохраняя её значение и положение.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `old` - Текущее имя переменной
+    /// * `new` - Новое имя переменной
+    ///
+    /// Renames a variable in the key section, keeping its value and position.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `old` - Current variable name
+    /// * `new` - New variable name
+    ///
+    /// # Errors
+    /// `Error::VarNotFound` if `old` does not exist, `Error::VarExists` if `new` is already taken.
+    pub fn rename_var(&self, key: &str, old: &str, new: &str) -> Result<(), Error> {
+        self.locked(|| {
+            if !self.is_ccf()? {
+                return Err(Error::Integrity);
+            }
+
+            let blocks = self.read_blocks()?;
+            let lines = self.var_lines(&blocks, key, old);
+
+            if lines.is_empty() {
+                return Err(Error::VarNotFound { key: key.trim().to_string(), var: old.trim().to_string() });
+            }
+
+            if old.trim() == new.trim() {
+                return Ok(());
+            }
+
+            if self.find_var(&blocks, key, new).is_some() {
+                return Err(Error::VarExists { key: key.trim().to_string(), var: new.trim().to_string() });
+            }
+
+            let source = self.storage.read()?;
+            let mut txt = String::new();
+
+            for (i, line) in source.lines().enumerate() {
+
+                if let Some((_, val)) = lines.iter().find(|(pos, _)| *pos == i) {
+                    txt.push_str(&entry_line(line, new.trim(), val));
+                    continue;
+                }
+
+                txt.push_str(line);
+                txt.push('\n');
+            }
+
+            self.commit("rename_var", &txt)?;
+
+            Ok(())
+        })
+    }
+
+    /// Переносит переменную из одной секции в конец другой.
+    /// Если целевой секции нет, она будет создана.
+    ///
+    /// # Аргументы
+    /// * `from` - Имя исходной секции
+    /// * `to` - Имя целевой секции
+    /// * `var` - Имя переменной
+    ///
+    /// Moves a variable from one section to the end of another.
+    /// If the target section does not exist, it will be created.
+    ///
+    /// # Arguments
+    /// * `from` - Source section name
+    /// * `to` - Target section name
+    /// * `var` - Variable name
+    ///
+    /// # Errors
+    /// `Error::VarNotFound` if `var` is not in `from`, `Error::VarExists` if `to` already has it.
+    pub fn move_var(&self, from: &str, to: &str, var: &str) -> Result<(), Error> {
+        self.locked(|| {
+            if !self.is_ccf()? {
+                return Err(Error::Integrity);
+            }
+
+            let blocks = self.read_blocks()?;
+
+            let val = match self.find_var(&blocks, from, var) {
+                Some((_, val)) => val,
+                None => return Err(Error::VarNotFound { key: from.trim().to_string(), var: var.trim().to_string() }),
+            };
+
+            if from.trim() == to.trim() {
+                return Ok(());
+            }
+
+            if self.find_var(&blocks, to, var).is_some() {
+                return Err(Error::VarExists { key: to.trim().to_string(), var: var.trim().to_string() });
+            }
+
+            let source = self.storage.read()?;
+            let removed: Vec<usize> = self.var_lines(&blocks, from, var).into_iter().map(|(i, _)| i).collect();
+            let close = self.target_block(&blocks, to).map(|b| b.close);
+            let mut txt = String::new();
+
+            for (i, line) in source.lines().enumerate() {
+
+                if removed.contains(&i) {
+                    continue;
+                }
+
+                if Some(i) == close {
+                    txt.push_str(&format!("\t{}={}\n", var.trim(), val));
+                }
+
+                txt.push_str(line);
+                txt.push('\n');
+            }
+
+            if close.is_none() {
+                txt.push_str(&format!("({}) {{\n\t{}={}\n}}\n", to.trim(), var.trim(), val));
+            }
+
+            self.commit("move_var", &txt)?;
+
+            Ok(())
+        })
+    }
+
+    /// Очищает весь файл, удаляя все данные.
+    ///
+    /// Clears the entire file, removing all data.
+    pub fn clear(&self) -> std::io::Result<()> {
+        self.locked(|| self.commit("clear", ""))
+    }
 
-        txt.truncate(new_len);
-        txt.push_str("\n}");
+    /// Атомарно сохраняет документ в файл: сначала во временный файл, затем переименованием.
+    /// Если документ получен из `load` и файл с тех пор изменили, запись не выполняется.
+    /// После успешной записи документ считается загруженным из новой версии файла.
+    /// Повторы, скрытые правилом `duplicates`, записываются обратно (см. `Document::shadowed`).
+    ///
+    /// # Аргументы
+    /// * `doc` - Документ для сохранения
+    ///
+    /// Atomically saves a document to the file: to a temporary file first, then by renaming it.
+    /// If the document came from `load` and the file has changed since, nothing is written.
+    /// After a successful write the document counts as loaded from the new version of the file.
+    /// Repeats hidden by the `duplicates` policy are written back (see `Document::shadowed`).
+    ///
+    /// # Arguments
+    /// * `doc` - Document to save
+    ///
+    /// # Errors
+    /// `Error::Conflict` with the changes made to the file since the document was loaded.
+    pub fn save(&self, doc: &Document) -> Result<(), Error> {
+        self.save_as("save", doc)
+    }
+
+    /// `save` под именем операции `operation` для журнала аудита.
+    pub(crate) fn save_as(&self, operation: &str, doc: &Document) -> Result<(), Error> {
+        self.locked(|| {
+            self.check_origin(doc)?;
+            self.write_doc(operation, doc)
+        })
+    }
+
+    /// Сохраняет документ, не проверяя, изменился ли файл после загрузки. Чужие правки теряются.
+    ///
+    /// # Аргументы
+    /// * `doc` - Документ для сохранения
+    ///
+    /// Saves the document without checking whether the file changed after loading. Foreign edits are lost.
+    ///
+    /// # Arguments
+    /// * `doc` - Document to save
+    pub fn save_force(&self, doc: &Document) -> Result<(), Error> {
+        self.locked(|| self.write_doc("save_force", doc))
+    }
 
-        let full_filename = String::from(&format!("{}.json", filename));
-        let mut file = OpenOptions::new().write(true).truncate(true).create(true).open(full_filename)?;
-        writeln!(file, "{}", txt)?;
+    fn write_doc(&self, operation: &str, doc: &Document) -> Result<(), Error> {
+        let txt = doc.render_with_shadowed();
+        self.commit(operation, &txt)?;
+        doc.set_origin(Origin::new(self.storage.stamp()?, &txt));
 
         Ok(())
     }
 
+    /// Записывает новое значение, только если текущее равно ожидаемому. Сравнение и запись
+    /// выполняются под эксклюзивной блокировкой хранилища. Возвращает `true`, если значение записано.
+    /// Ту же блокировку берёт каждый изменяющий метод (`set`, `add`, `edit`, `delete_*`, переименования,
+    /// `save` и другие), поэтому между сравнением и записью файл никто не изменит.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `expected` - Ожидаемое значение; `None` — переменной не должно быть
+    /// * `new` - Новое значение
+    ///
+    /// # Пример
+    /// ```
+    /// # use tnt::{MemoryStorage, TNT};
+    /// let tnt = TNT::with_storage(MemoryStorage::new(""));
+    ///
+    /// assert!(tnt.compare_and_set("cron", "last_run", None, "1700000000").unwrap());
+    /// assert!(!tnt.compare_and_set("cron", "last_run", None, "1700000060").unwrap());
+    /// assert!(tnt.compare_and_set("cron", "last_run", Some("1700000000"), "1700000060").unwrap());
+    /// ```
+    ///
+    /// Writes the new value only if the current one equals the expected value. The comparison and the write
+    /// happen under an exclusive storage lock. Returns `true` if the value was written.
+    /// Every mutating method (`set`, `add`, `edit`, `delete_*`, the renames, `save` and the rest) takes
+    /// the same lock, so nobody changes the file between the comparison and the write.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `expected` - Expected value; `None` means the variable must not exist
+    /// * `new` - New value
+    ///
+    /// # Errors
+    /// `Error::Integrity` if the file fails the integrity check; nothing is written then.
+    pub fn compare_and_set<V: std::fmt::Display>(&self, key: &str, var: &str, expected: Option<&str>, new: V) -> Result<bool, Error> {
+        self.locked(|| {
+            let doc = self.load()?;
+
+            if doc.get(key, var) != expected {
+                return Ok(false);
+            }
+
+            self.upsert(&[(key, var, new)])?;
+
+            Ok(true)
+        })
+    }
+
+    /// Прибавляет `delta` к целому значению переменной под эксклюзивной блокировкой хранилища
+    /// и возвращает новое значение. Отсутствующая переменная считается равной 0.
+    /// Атомарность — как у `compare_and_set`.
+    ///
+    /// # Аргументы
+    /// * `key` - Имя секции (ключа)
+    /// * `var` - Имя переменной
+    /// * `delta` - Приращение (может быть отрицательным)
+    ///
+    /// # Пример
+    /// ```
+    /// # use tnt::{MemoryStorage, TNT};
+    /// let tnt = TNT::with_storage(MemoryStorage::new(""));
+    ///
+    /// assert_eq!(tnt.increment("stats", "runs", 1).unwrap(), 1);
+    /// assert_eq!(tnt.increment("stats", "runs", 5).unwrap(), 6);
+    /// ```
+    ///
+    /// Adds `delta` to the integer value of a variable under an exclusive storage lock
+    /// and returns the new value. A missing variable counts as 0.
+    /// Atomicity is the same as for `compare_and_set`.
+    ///
+    /// # Arguments
+    /// * `key` - Section (key) name
+    /// * `var` - Variable name
+    /// * `delta` - Increment (may be negative)
+    ///
+    /// # Errors
+    /// `Error::Parse` if the current value is not an integer or the sum overflows,
+    /// `Error::Integrity` if the file fails the integrity check.
+    pub fn increment(&self, key: &str, var: &str, delta: i64) -> Result<i64, Error> {
+        self.locked(|| {
+            let doc = self.load()?;
+            let current = match doc.get(key, var) {
+                Some(val) => val
+                    .trim()
+                    .parse::<i64>()
+                    .map_err(|_| Error::Parse(format!("'{}.{}' is not an integer: '{}'", key.trim(), var.trim(), val)))?,
+                None => 0,
+            };
+
+            let new = current
+                .checked_add(delta)
+                .ok_or_else(|| Error::Parse(format!("'{}.{}' overflows: {} + {}", key.trim(), var.trim(), current, delta)))?;
+            self.upsert(&[(key, var, new)])?;
+
+            Ok(new)
+        })
+    }
+
+    /// Выполняет `f` под эксклюзивной блокировкой хранилища.
+    pub(crate) fn locked<R, E: From<std::io::Error>>(&self, f: impl FnOnce() -> Result<R, E>) -> Result<R, E> {
+        self.storage.lock()?;
+
+        let result = f();
+        self.storage.unlock()?;
+
+        result
+    }
+
     /// Импортирует данные из TOML-файла в основной файл.
     ///
     /// # Аргументы
@@ -650,43 +1427,44 @@ impl TNT {
     /// # Arguments
     /// * `toml` - TOML file name (without extension) from which data will be imported.
     pub fn from_toml(&self, toml: &str) -> std::io::Result<()> {
-        let full_toml = String::from(&format!("{}.toml", toml));
-        let file = File::open(full_toml)?;
-        let reader = BufReader::new(&file);
-        let mut txt = String::new();
-        let mut is_one = 0;
-
-        for line in reader.lines() {
-            let line = line?;
-
-            if line.find('[').is_some() && line.find(']').is_some() {
-                let cleaned_line = line.replace(['[', ']'], "");
-                let key = cleaned_line.trim();
-                if is_one > 0 {
-                    txt.push_str(&format!("}}\n({}) {{\n", key));
-                } else {
-                    txt.push_str(&format!("({}) {{\n", key));
+        self.locked(|| {
+            let full_toml = String::from(&format!("{}.toml", toml));
+            let file = File::open(full_toml)?;
+            let reader = BufReader::new(&file);
+            let mut txt = String::new();
+            let mut is_one = 0;
+
+            for line in reader.lines() {
+                let line = line?;
+
+                if line.find('[').is_some() && line.find(']').is_some() {
+                    let cleaned_line = line.replace(['[', ']'], "");
+                    let key = cleaned_line.trim();
+                    if is_one > 0 {
+                        txt.push_str(&format!("}}\n({}) {{\n", key));
+                    } else {
+                        txt.push_str(&format!("({}) {{\n", key));
+                    }
+                    is_one += 1;
+                    continue;
                 }
-                is_one += 1;
-                continue;
-            }
 
-            if !line.is_empty() {
-                let fmt_line = line.replace(['\"', ' '], "");
-                let fmt_line_2 = fmt_line.trim();
-                let var = fmt_line_2.split('=').next().unwrap_or(fmt_line_2);
-                let val = fmt_line_2.split('=').nth(1).unwrap_or("");
+                if !line.is_empty() {
+                    let fmt_line = line.replace(['\"', ' '], "");
+                    let fmt_line_2 = fmt_line.trim();
+                    let var = fmt_line_2.split('=').next().unwrap_or(fmt_line_2);
+                    let val = fmt_line_2.split('=').nth(1).unwrap_or("");
 
-                txt.push_str(&format!("\t{}={}\n", var, val));
+                    txt.push_str(&format!("\t{}={}\n", var, val));
+                }
             }
-        }
 
-        txt.push_str("}");
+            txt.push('}');
 
-        let mut file = OpenOptions::new().write(true).truncate(true).create(true).open(&self.filename)?;
-        writeln!(file, "{}", txt)?;
+            self.commit("from_toml", &format!("{}\n", txt))?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Импортирует данные из JSON-файла в основной файл.
@@ -699,42 +1477,140 @@ impl TNT {
     /// # Arguments
     /// * `json` - JSON file name (without extension) from which data will be imported.
     pub fn from_json(&self, json: &str) -> std::io::Result<()> {
-        let full_json = String::from(&format!("{}.json", json));
-        let file = File::open(full_json)?;
-        let reader = BufReader::new(&file);
-        let mut txt = String::new();
-        let mut is_key = false;
+        self.locked(|| {
+            let full_json = String::from(&format!("{}.json", json));
+            let file = File::open(full_json)?;
+            let reader = BufReader::new(&file);
+            let mut txt = String::new();
+            let mut is_key = false;
 
 
-        for line in reader.lines() {
-            let line = line?;
+            for line in reader.lines() {
+                let line = line?;
 
-            if line.find('\"').is_some() && line.find("{").is_some() && line.find(':').is_some() {
-                let cleaned_line = line.replace(['{', ':', '\"'], "");
-                let key = cleaned_line.trim();
-                txt.push_str(&format!("({}) {{\n", key));
-                is_key = true;
-                continue;
-            }
+                if line.find('\"').is_some() && line.find("{").is_some() && line.find(':').is_some() {
+                    let cleaned_line = line.replace(['{', ':', '\"'], "");
+                    let key = cleaned_line.trim();
+                    txt.push_str(&format!("({}) {{\n", key));
+                    is_key = true;
+                    continue;
+                }
 
-            if is_key && line.find('}').is_some() {
-                is_key = false;
-                txt.push_str("}\n");
-                continue;
+                if is_key && line.find('}').is_some() {
+                    is_key = false;
+                    txt.push_str("}\n");
+                    continue;
+                }
+
+                if is_key && !line.is_empty() {
+                    let cleaned_line = line.replace(['\"', ',', ' '], "");
+                    let var_and_val = cleaned_line.trim();
+                    let var = var_and_val.split(':').next().unwrap_or(var_and_val);
+                    let val = var_and_val.split(':').nth(1).unwrap_or("");
+                    txt.push_str(&format!("\t{}={}\n", var, val));
+                }
             }
 
-            if is_key && !line.is_empty() {
-                let cleaned_line = line.replace(['\"', ',', ' '], "");
-                let var_and_val = cleaned_line.trim();
-                let var = var_and_val.split(':').next().unwrap_or(var_and_val);
-                let val = var_and_val.split(':').nth(1).unwrap_or("");
-                txt.push_str(&format!("\t{}={}\n", var, val));
+            self.commit("from_json", &format!("{}\n", txt))?;
+
+            Ok(())
+        })
+    }
+}
+
+fn entry_line(old_line: &str, var: &str, val: &str) -> String {
+    let old_val = old_line.trim().split_once('=').map(|(_, v)| v).unwrap_or("");
+
+    if secret::strip_marker(old_val).is_some() && secret::strip_marker(val).is_none() {
+        format!("\t{}={} {}\n", var, secret::MARKER, val)
+    } else {
+        format!("\t{}={}\n", var, val)
+    }
+}
+
+fn shown<'a>(section: &Section, var: &str, val: &'a str, reveal: bool) -> &'a str {
+    if !reveal && section.is_secret(var) {
+        secret::REDACTED
+    } else {
+        val
+    }
+}
+
+pub(crate) fn export_toml(doc: &Document, filename: &str, reveal: bool) -> std::io::Result<()> {
+    let full_filename = String::from(&format!("{}.toml", filename));
+    let mut file = fs::OpenOptions::new().write(true).truncate(true).create(true).open(full_filename)?;
+    write!(file, "{}", toml_text(doc, reveal))?;
+
+    Ok(())
+}
+
+pub(crate) fn toml_text(doc: &Document, reveal: bool) -> String {
+    let mut txt: String = String::new();
+
+    for section in doc.sections() {
+        txt.push_str(&format!("[{}]\n", toml_key(section.name())));
+
+        for (var, val) in section.entries() {
+            let val = shown(section, var, val, reveal);
+
+            if list::is_list(val) {
+                txt.push_str(&format!("{}={}\n", toml_key(var), list::to_array(val)));
+            } else {
+                txt.push_str(&format!("{}=\"{}\"\n", toml_key(var), json::escape(val)));
             }
         }
 
-        let mut file = OpenOptions::new().write(true).truncate(true).create(true).open(&self.filename)?;
-        writeln!(file, "{}", txt)?;
+        txt.push('\n');
+    }
 
-        Ok(())
+    txt
+}
+
+// A name that is not a bare TOML key (e.g. `db @prod`) is written as a quoted key.
+fn toml_key(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        name.to_string()
+    } else {
+        format!("\"{}\"", json::escape(name))
     }
 }
+
+pub(crate) fn export_json(doc: &Document, filename: &str, reveal: bool) -> std::io::Result<()> {
+    let full_filename = String::from(&format!("{}.json", filename));
+    let mut file = fs::OpenOptions::new().write(true).truncate(true).create(true).open(full_filename)?;
+    writeln!(file, "{}", json_text(doc, reveal))?;
+
+    Ok(())
+}
+
+pub(crate) fn json_text(doc: &Document, reveal: bool) -> String {
+    if doc.sections().is_empty() {
+        return "{}".to_string();
+    }
+
+    let mut sections = Vec::new();
+
+    for section in doc.sections() {
+        let entries: Vec<String> = section
+            .entries()
+            .iter()
+            .map(|(var, val)| {
+                let val = shown(section, var, val, reveal);
+
+                if list::is_list(val) {
+                    format!("\t\t\"{}\": {}", json::escape(var), list::to_array(val))
+                } else {
+                    format!("\t\t\"{}\": \"{}\"", json::escape(var), json::escape(val))
+                }
+            })
+            .collect();
+
+        if entries.is_empty() {
+            sections.push(format!("\t\"{}\": {{\n\t}}", json::escape(section.name())));
+        } else {
+            sections.push(format!("\t\"{}\": {{\n{}\n\t}}", json::escape(section.name()), entries.join(",\n")));
+        }
+    }
+
+    format!("{{\n{}\n}}", sections.join(",\n\n"))
+}
@@ -0,0 +1,190 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::diff::{self, Change};
+use crate::document::Document;
+use crate::error::Error;
+use crate::storage::{FileStorage, Storage};
+use crate::tnt::TNT;
+
+type Subscriber = Box<dyn Fn(&[Change], &Document) + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fingerprint {
+    stamp: Option<(SystemTime, u64)>,
+    hash: u64,
+}
+
+/// Наблюдатель за TNT-файлом, опрашивающий его на изменения.
+/// Хранит последний корректный снимок; некорректная правка его не заменяет.
+///
+/// A watcher that polls a TNT file for changes.
+/// It keeps the last good snapshot; an invalid edit does not replace it.
+///
+/// # Example
+/// ```no_run
+/// # use std::time::Duration;
+/// # use tnt::TNT;
+/// let tnt = TNT::open("app.tnt").unwrap();
+/// let mut watcher = tnt.watch().unwrap();
+///
+/// watcher.subscribe(|changes, _doc| println!("{:?}", changes));
+/// let handle = watcher.interval(Duration::from_secs(2)).spawn();
+///
+/// let port = handle.snapshot().get("db", "port").map(str::to_string);
+/// handle.stop();
+/// ```
+pub struct Watcher<S: Storage = FileStorage> {
+    tnt: TNT<S>,
+    interval: Duration,
+    fingerprint: Option<Fingerprint>,
+    snapshot: Arc<RwLock<Document>>,
+    subscribers: Vec<Subscriber>,
+}
+
+/// Дескриптор фонового наблюдателя, запущенного через `Watcher::spawn`.
+///
+/// A handle to a background watcher started with `Watcher::spawn`.
+pub struct WatchHandle {
+    snapshot: Arc<RwLock<Document>>,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl<S: Storage + Clone> TNT<S> {
+    /// Начинает наблюдение за файлом. Файл должен быть корректным в момент вызова.
+    ///
+    /// Starts watching the file. The file must be valid at the time of the call.
+    pub fn watch(&self) -> Result<Watcher<S>, Error> {
+        let mut watcher = Watcher {
+            tnt: self.clone(),
+            interval: Duration::from_secs(1),
+            fingerprint: None,
+            snapshot: Arc::new(RwLock::new(Document::new())),
+            subscribers: Vec::new(),
+        };
+
+        watcher.poll()?;
+
+        Ok(watcher)
+    }
+}
+
+impl<S: Storage> Watcher<S> {
+    /// Задаёт интервал опроса файла (по умолчанию одна секунда).
+    ///
+    /// Sets the polling interval (one second by default).
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Регистрирует подписчика, вызываемого со списком изменений и новым снимком.
+    /// Подписчики вызываются после замены снимка, поэтому могут читать `snapshot()`.
+    ///
+    /// Registers a subscriber called with the list of changes and the new snapshot.
+    /// Subscribers are called after the snapshot is replaced, so they may read `snapshot()`.
+    pub fn subscribe<F>(&mut self, subscriber: F)
+    where
+        F: Fn(&[Change], &Document) + Send + 'static,
+    {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    /// Последний корректный снимок документа.
+    ///
+    /// The last good snapshot of the document.
+    pub fn snapshot(&self) -> Document {
+        self.snapshot.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Однократно проверяет файл. Если он изменился и корректен, обновляет снимок,
+    /// уведомляет подписчиков и возвращает изменения.
+    /// Если файл некорректен, возвращает ошибку и сохраняет прежний снимок.
+    ///
+    /// Checks the file once. If it changed and is valid, updates the snapshot,
+    /// notifies the subscribers and returns the changes.
+    /// If the file is invalid, returns an error and keeps the previous snapshot.
+    pub fn poll(&mut self) -> Result<Vec<Change>, Error> {
+        let stamp = self.tnt.storage().stamp()?;
+
+        if let Some(old) = self.fingerprint
+            && stamp.is_some()
+            && old.stamp == stamp
+        {
+            return Ok(Vec::new());
+        }
+
+        let txt = self.tnt.storage().read()?;
+        let mut hasher = DefaultHasher::new();
+        txt.hash(&mut hasher);
+
+        let fingerprint = Fingerprint { stamp, hash: hasher.finish() };
+
+        if self.fingerprint.is_some_and(|old| old.hash == fingerprint.hash) {
+            self.fingerprint = Some(fingerprint);
+            return Ok(Vec::new());
+        }
+
+        let mut doc = Document::parse(&txt)?;
+        doc.dedup(self.tnt.duplicate_policy())?;
+        let is_first = self.fingerprint.is_none();
+        self.fingerprint = Some(fingerprint);
+
+        let changes = if is_first { Vec::new() } else { diff::diff(&self.snapshot(), &doc).changes().to_vec() };
+        *self.snapshot.write().unwrap_or_else(|e| e.into_inner()) = doc.clone();
+
+        // Subscribers run without the snapshot lock held, so they may read it themselves.
+        if !changes.is_empty() {
+            for subscriber in &self.subscribers {
+                subscriber(&changes, &doc);
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// Запускает опрос в фоновом потоке.
+    /// Ошибки чтения и некорректные правки пропускаются до следующего опроса.
+    ///
+    /// Starts polling in a background thread.
+    /// Read errors and invalid edits are skipped until the next poll.
+    pub fn spawn(mut self) -> WatchHandle
+    where
+        S: Send + 'static,
+    {
+        let snapshot = Arc::clone(&self.snapshot);
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+
+        let thread = thread::spawn(move || {
+            while !stop_flag.load(Ordering::Relaxed) {
+                thread::sleep(self.interval);
+                let _ = self.poll();
+            }
+        });
+
+        WatchHandle { snapshot, stop, thread }
+    }
+}
+
+impl WatchHandle {
+    /// Последний корректный снимок документа.
+    ///
+    /// The last good snapshot of the document.
+    pub fn snapshot(&self) -> Document {
+        self.snapshot.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Останавливает фоновый поток и дожидается его завершения.
+    ///
+    /// Stops the background thread and waits for it to finish.
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.thread.join();
+    }
+}
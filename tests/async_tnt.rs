@@ -0,0 +1,115 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use std::io::ErrorKind;
+use std::thread;
+use std::time::Duration;
+
+use common::Scratch;
+use tnt::{AsyncTnt, DuplicatePolicy, Error, FileStorage, WritableStorage};
+
+#[tokio::test]
+async fn reads_and_writes_match_the_sync_api() {
+    let scratch = Scratch::new("async-basic");
+    let path = scratch.write("app.tnt", "(db) {\n\thost=h\n\turl=pg://${db.host}\n}\n");
+    let tnt = AsyncTnt::open(&path).await.unwrap();
+
+    assert_eq!(tnt.get("db", "url").await.unwrap(), "pg://h");
+    assert_eq!(tnt.get_raw("db", "url").await.unwrap(), "pg://${db.host}");
+
+    tnt.set("db", "port", 5432).await.unwrap();
+    tnt.delete_var("db", "url").await.unwrap();
+    tnt.set("cache", "ttl", 60).await.unwrap();
+    tnt.delete_key("cache").await.unwrap();
+
+    assert_eq!(scratch.read("app.tnt"), "(db) {\n\thost=h\n\tport=5432\n}\n");
+    assert_eq!(tnt.path(), path.as_path());
+}
+
+#[tokio::test]
+async fn open_and_create_modes() {
+    let scratch = Scratch::new("async-open");
+    let path = scratch.path("app.tnt");
+
+    assert!(matches!(AsyncTnt::open(&path).await, Err(Error::Io(err)) if err.kind() == ErrorKind::NotFound));
+    assert!(matches!(AsyncTnt::open(scratch.dir()).await, Err(Error::Io(err)) if err.kind() == ErrorKind::InvalidInput));
+
+    AsyncTnt::create(&path).await.unwrap().set("a", "x", 1).await.unwrap();
+    AsyncTnt::create(&path).await.unwrap();
+    assert_eq!(scratch.read("app.tnt"), "(a) {\n\tx=1\n}\n");
+}
+
+#[tokio::test]
+async fn concurrent_writes_are_not_lost() {
+    let scratch = Scratch::new("async-concurrent");
+    let path = scratch.write("app.tnt", "");
+    let tnt = AsyncTnt::open(&path).await.unwrap();
+
+    let tasks: Vec<_> = (0..20)
+        .map(|i| {
+            let tnt = tnt.clone();
+            tokio::spawn(async move { tnt.set("workers", format!("w{}", i), i).await })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    let doc = tnt.load().await.unwrap();
+    assert_eq!(doc.entries("workers").len(), 20);
+}
+
+#[tokio::test]
+async fn save_detects_foreign_edits() {
+    let scratch = Scratch::new("async-conflict");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+    let tnt = AsyncTnt::open(&path).await.unwrap();
+
+    let mut doc = tnt.load().await.unwrap();
+    doc.set("db", "port", 6432).unwrap();
+    scratch.write("app.tnt", "(db) {\n\tport=7432\n}\n");
+
+    assert!(matches!(tnt.save(&doc).await, Err(Error::Conflict(_))));
+    assert_eq!(scratch.read("app.tnt"), "(db) {\n\tport=7432\n}\n");
+
+    tnt.save_force(&doc).await.unwrap();
+    assert_eq!(scratch.read("app.tnt"), "(db) {\n\tport=6432\n}\n");
+}
+
+#[tokio::test]
+async fn duplicates_and_exports() {
+    let scratch = Scratch::new("async-export");
+    let path = scratch.write("app.tnt", "(a) {\n\tx=1\n\tx=2\n\tpassword=hunter2\n}\n");
+    let tnt = AsyncTnt::open(&path).await.unwrap().duplicates(DuplicatePolicy::LastWins);
+
+    assert_eq!(tnt.get("a", "x").await.unwrap(), "2");
+
+    tnt.to_json(&scratch.stem("out")).await.unwrap();
+    tnt.to_toml(&scratch.stem("out")).await.unwrap();
+
+    assert!(!scratch.read("out.json").contains("hunter2"));
+    assert!(!scratch.read("out.toml").contains("hunter2"));
+}
+
+#[tokio::test]
+async fn writes_wait_for_the_file_lock() {
+    let scratch = Scratch::new("async-lock");
+    let path = scratch.write("app.tnt", "(a) {\n}\n");
+    let tnt = AsyncTnt::open(&path).await.unwrap();
+
+    // Another writer holds the lock and replaces the file before releasing it.
+    let storage = FileStorage::new(&path);
+    storage.lock().unwrap();
+    let writer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        storage.write("(a) {\n\tx=1\n}\n").unwrap();
+        storage.unlock().unwrap();
+    });
+
+    tnt.set("a", "y", 2).await.unwrap();
+    writer.join().unwrap();
+
+    assert_eq!(scratch.read("app.tnt"), "(a) {\n\tx=1\n\ty=2\n}\n");
+}
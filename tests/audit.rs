@@ -0,0 +1,208 @@
+mod common;
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use common::Scratch;
+use tnt::{AuditRecord, AuditSink, JsonLinesSink, MemoryStorage, Migrator, Patch, TNT};
+
+type Records = Arc<Mutex<Vec<AuditRecord>>>;
+
+fn recorder() -> (Records, impl AuditSink + 'static) {
+    let records: Records = Arc::default();
+    let sink = Arc::clone(&records);
+
+    (records, move |record: &AuditRecord| {
+        sink.lock().unwrap().push(record.clone());
+        Ok(())
+    })
+}
+
+fn record_at(secs: u64, millis: u64) -> AuditRecord {
+    AuditRecord {
+        time: UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_millis(millis),
+        operation: "set".to_string(),
+        actor: "alice".to_string(),
+        section: "db".to_string(),
+        var: None,
+        old: None,
+        new: None,
+    }
+}
+
+fn timestamp(record: &AuditRecord) -> String {
+    let json = record.to_json();
+    let start = json.find("\"timestamp\": \"").unwrap() + 14;
+
+    json[start..start + 24].to_string()
+}
+
+#[test]
+fn records_one_entry_per_changed_variable() {
+    let (records, sink) = recorder();
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\tport=5432\n}\n")).audit(sink, "alice");
+
+    tnt.set("db", "port", 6432).unwrap();
+    tnt.add("db", "host", "h").unwrap();
+    tnt.delete_var("db", "host").unwrap();
+
+    let records = records.lock().unwrap();
+    let summary: Vec<_> =
+        records.iter().map(|r| (r.operation.as_str(), r.var.as_deref(), r.old.as_deref(), r.new.as_deref())).collect();
+
+    assert_eq!(
+        summary,
+        [
+            ("set", Some("port"), Some("5432"), Some("6432")),
+            ("add", Some("host"), None, Some("h")),
+            ("delete_var", Some("host"), Some("h"), None),
+        ]
+    );
+    assert!(records.iter().all(|r| r.actor == "alice" && r.section == "db"));
+}
+
+#[test]
+fn document_level_writes_keep_their_operation_names() {
+    let (records, sink) = recorder();
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\tport=5432\n}\n")).audit(sink, "alice");
+
+    tnt.apply(&Patch::new().set("db", "port", 1)).unwrap();
+    Migrator::new().step(1, "pool", |doc| doc.set("db", "pool", 10)).migrate(&tnt).unwrap();
+
+    let mut doc = tnt.load().unwrap();
+    doc.set("db", "port", 2).unwrap();
+    tnt.save(&doc).unwrap();
+    doc.set("db", "port", 3).unwrap();
+    tnt.save_force(&doc).unwrap();
+
+    let shared = tnt.shared().unwrap();
+    shared.set("db", "port", 4).unwrap();
+    shared.update(|doc| doc.set("db", "port", 5)).unwrap();
+
+    let records = records.lock().unwrap();
+    let operations: Vec<_> = records.iter().filter(|r| r.var.as_deref() == Some("port")).map(|r| r.operation.as_str()).collect();
+    assert_eq!(operations, ["apply", "save", "save_force", "set", "update"]);
+    assert!(records.iter().any(|r| r.operation == "migrate" && r.var.as_deref() == Some("pool")));
+}
+
+#[test]
+fn unchanged_text_sends_no_records() {
+    let (records, sink) = recorder();
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\tport=5432\n}\n")).audit(sink, "alice");
+
+    tnt.set("db", "port", 5432).unwrap();
+
+    assert!(records.lock().unwrap().is_empty());
+}
+
+#[test]
+fn sink_error_cancels_the_change() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone()).audit(|_: &AuditRecord| Err(io::Error::other("sink is down")), "alice");
+
+    let err = tnt.set("db", "port", 6432).unwrap_err();
+
+    assert!(err.to_string().contains("sink is down"));
+    assert_eq!(storage.text(), "(db) {\n\tport=5432\n}\n");
+}
+
+#[test]
+fn secret_values_are_hashed_with_sha256() {
+    let vectors = [
+        ("", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+        ("abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+        (
+            "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+    ];
+
+    for (val, hash) in vectors {
+        let (records, sink) = recorder();
+        let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\thost=h\n}\n")).audit(sink, "alice");
+
+        tnt.set_secret("db", "password", val).unwrap();
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 1, "{:?}", val);
+        assert_eq!(records[0].new, Some(format!("sha256:{}", hash)), "{:?}", val);
+    }
+}
+
+#[test]
+fn unparsable_text_is_recorded_as_hashes() {
+    let (records, sink) = recorder();
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\tpassword=hunter2\n")).audit(sink, "alice");
+
+    tnt.clear().unwrap();
+
+    let records = records.lock().unwrap();
+    assert_eq!(records[0].section, "");
+    assert!(records[0].old.as_deref().unwrap().starts_with("sha256:"));
+    assert_eq!(records[0].new.as_deref(), Some("sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
+}
+
+#[test]
+fn timestamps_are_rfc3339() {
+    assert_eq!(timestamp(&record_at(0, 0)), "1970-01-01T00:00:00.000Z");
+    assert_eq!(timestamp(&record_at(1_709_164_800 + 86_399, 999)), "2024-02-29T23:59:59.999Z");
+    assert_eq!(timestamp(&record_at(1_709_164_800 + 86_400, 0)), "2024-03-01T00:00:00.000Z");
+    assert_eq!(timestamp(&record_at(951_782_400, 5)), "2000-02-29T00:00:00.005Z");
+    assert_eq!(timestamp(&record_at(4_107_456_000 + 86_400, 0)), "2100-03-01T00:00:00.000Z");
+}
+
+#[test]
+fn json_lines_sink_appends_one_object_per_line() {
+    let scratch = Scratch::new("audit-jsonl");
+    let log = scratch.path("audit.jsonl");
+    let tnt = TNT::with_storage(MemoryStorage::new("")).audit(JsonLinesSink::open(&log).unwrap(), "deploy \"bot\"");
+
+    tnt.add("db", "port", 5432).unwrap();
+    tnt.set_secret("db", "password", "hunter2").unwrap();
+
+    let text = scratch.read("audit.jsonl");
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("\"operation\": \"add\""));
+    assert!(lines[0].contains("\"actor\": \"deploy \\\"bot\\\"\""));
+    assert!(lines[0].contains("\"var\": \"port\", \"old\": null, \"new\": \"5432\""));
+    assert!(!text.contains("hunter2"));
+}
+
+#[test]
+fn composed_changes_are_audited() {
+    let scratch = Scratch::new("audit-compose");
+    scratch.write("base.tnt", "(db) {\n\tport=5432\n}\n");
+    let main = scratch.write("app.tnt", "@include base.tnt\n(app) {\n\tname=x\n}\n");
+
+    let (records, sink) = recorder();
+    let mut composed = TNT::open(&main).unwrap().audit(sink, "alice").compose().unwrap();
+    composed.set("db", "port", 6432).unwrap();
+    composed.delete_var("app", "name").unwrap();
+
+    let records = records.lock().unwrap();
+    let summary: Vec<_> =
+        records.iter().map(|r| (r.operation.as_str(), r.section.as_str(), r.old.as_deref(), r.new.as_deref())).collect();
+    assert_eq!(summary, [("set", "db", Some("5432"), Some("6432")), ("delete_var", "app", Some("x"), None)]);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_changes_are_audited() {
+    let scratch = Scratch::new("audit-async");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let (records, sink) = recorder();
+    let tnt = tnt::AsyncTnt::open(&path).await.unwrap().audit(sink, "alice");
+    tnt.set("db", "port", 6432).await.unwrap();
+
+    let failing = tnt::AsyncTnt::open(&path).await.unwrap().audit(|_: &AuditRecord| Err(io::Error::other("down")), "bob");
+    assert!(failing.set("db", "port", 7432).await.is_err());
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!((records[0].old.as_deref(), records[0].new.as_deref()), (Some("5432"), Some("6432")));
+    assert_eq!(scratch.read("app.tnt"), "(db) {\n\tport=6432\n}\n");
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use common::Scratch;
+use tnt::{Error, MemoryStorage, Storage, TNT, WritableStorage};
+
+/// Memory storage whose text turns unbalanced after the first read,
+/// as if an unlocked writer broke the file in between.
+#[derive(Default)]
+struct BreaksAfterFirstRead {
+    inner: MemoryStorage,
+    reads: AtomicUsize,
+}
+
+impl Storage for BreaksAfterFirstRead {
+    fn read(&self) -> io::Result<String> {
+        match self.reads.fetch_add(1, Ordering::SeqCst) {
+            0 => self.inner.read(),
+            _ => Ok("(a) {\n\tn=1\n".to_string()),
+        }
+    }
+}
+
+impl WritableStorage for BreaksAfterFirstRead {
+    fn write(&self, text: &str) -> io::Result<()> {
+        self.inner.write(text)
+    }
+}
+
+fn breaking() -> TNT<BreaksAfterFirstRead> {
+    let storage = BreaksAfterFirstRead::default();
+    storage.inner.write("(a) {\n\tn=1\n}\n").unwrap();
+
+    TNT::with_storage(storage)
+}
+
+#[test]
+fn compare_and_set_reports_integrity_failure() {
+    let tnt = breaking();
+
+    assert!(matches!(tnt.compare_and_set("a", "n", Some("1"), 2), Err(Error::Integrity)));
+    assert_eq!(tnt.storage().inner.text(), "(a) {\n\tn=1\n}\n");
+}
+
+#[test]
+fn increment_reports_integrity_failure() {
+    let tnt = breaking();
+
+    assert!(matches!(tnt.increment("a", "n", 1), Err(Error::Integrity)));
+    assert_eq!(tnt.storage().inner.text(), "(a) {\n\tn=1\n}\n");
+}
+
+#[test]
+fn compare_and_set_writes_only_on_match() {
+    let tnt = TNT::with_storage(MemoryStorage::new("(cron) {\n\tlast_run=100\n}\n"));
+
+    assert!(!tnt.compare_and_set("cron", "last_run", Some("99"), 200).unwrap());
+    assert!(!tnt.compare_and_set("cron", "last_run", None, 200).unwrap());
+    assert_eq!(tnt.get("cron", "last_run").unwrap(), "100");
+
+    assert!(tnt.compare_and_set("cron", "last_run", Some("100"), 200).unwrap());
+    assert_eq!(tnt.get("cron", "last_run").unwrap(), "200");
+
+    assert!(tnt.compare_and_set("cron", "owner", None, "worker-1").unwrap());
+    assert_eq!(tnt.get("cron", "owner").unwrap(), "worker-1");
+}
+
+#[test]
+fn compare_and_set_lets_exactly_one_contender_win() {
+    let storage = MemoryStorage::new("");
+    let wins: usize = thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let tnt = TNT::with_storage(storage.clone());
+                scope.spawn(move || tnt.compare_and_set("job", "owner", None, i).unwrap())
+            })
+            .collect();
+
+        handles.into_iter().map(|handle| handle.join().unwrap() as usize).sum()
+    });
+
+    assert_eq!(wins, 1);
+}
+
+#[test]
+fn increment_treats_a_missing_variable_as_zero() {
+    let tnt = TNT::with_storage(MemoryStorage::new(""));
+
+    assert_eq!(tnt.increment("stats", "runs", 3).unwrap(), 3);
+    assert_eq!(tnt.increment("stats", "runs", -5).unwrap(), -2);
+    assert_eq!(tnt.get("stats", "runs").unwrap(), "-2");
+}
+
+#[test]
+fn increment_rejects_non_integers_and_overflow() {
+    let tnt = TNT::with_storage(MemoryStorage::new(&format!("(stats) {{\n\tname=x\n\tmax={}\n}}\n", i64::MAX)));
+
+    assert!(matches!(tnt.increment("stats", "name", 1), Err(Error::Parse(_))));
+    assert!(matches!(tnt.increment("stats", "max", 1), Err(Error::Parse(_))));
+    assert_eq!(tnt.get("stats", "name").unwrap(), "x");
+}
+
+#[test]
+fn concurrent_increments_in_memory_are_not_lost() {
+    let storage = MemoryStorage::new("");
+
+    thread::scope(|scope| {
+        for _ in 0..8 {
+            let tnt = TNT::with_storage(storage.clone());
+            scope.spawn(move || {
+                for _ in 0..25 {
+                    tnt.increment("stats", "runs", 1).unwrap();
+                }
+            });
+        }
+    });
+
+    assert_eq!(TNT::with_storage(storage).get("stats", "runs").unwrap(), "200");
+}
+
+#[test]
+fn concurrent_increments_on_a_file_are_not_lost() {
+    let scratch = Scratch::new("cas");
+    let path = scratch.write("app.tnt", "");
+
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            let path = &path;
+            scope.spawn(move || {
+                let tnt = TNT::open(path).unwrap();
+                for _ in 0..25 {
+                    tnt.increment("stats", "runs", 1).unwrap();
+                }
+            });
+        }
+    });
+
+    assert_eq!(TNT::open(&path).unwrap().get("stats", "runs").unwrap(), "100");
+}
+
+#[test]
+fn plain_writes_do_not_lose_concurrent_increments() {
+    let scratch = Scratch::new("cas-mixed");
+    let path = scratch.write("app.tnt", "");
+
+    thread::scope(|scope| {
+        for t in 0..4 {
+            let path = &path;
+            scope.spawn(move || {
+                let tnt = TNT::open(path).unwrap();
+                for i in 0..25 {
+                    tnt.increment("stats", "runs", 1).unwrap();
+                    tnt.set("seen", format!("t{}_{}", t, i), i).unwrap();
+                    tnt.push("log", "items", format!("{}.{}", t, i)).unwrap();
+                    tnt.delete_var("seen", format!("t{}_{}", t, i)).unwrap();
+                }
+            });
+        }
+    });
+
+    let tnt = TNT::open(&path).unwrap();
+    assert_eq!(tnt.get("stats", "runs").unwrap(), "100");
+    assert_eq!(tnt.get_list("log", "items").unwrap().len(), 100);
+    assert!(tnt.vars("seen").unwrap().is_empty());
+    assert!(scratch.dir().join("app.tnt.lock").exists());
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::Scratch;
+use tnt::{Change, Document, Error, MemoryStorage, Storage, TNT, WritableStorage};
+
+#[test]
+fn debug_output_does_not_leak_secrets() {
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\tpassword=!secret hunter2\n\ttoken=abc\n}\n"));
+    let doc = tnt.load().unwrap();
+
+    let debug = format!("{:?}", doc);
+    assert!(!debug.contains("hunter2"), "{}", debug);
+    assert!(!debug.contains("abc"), "{}", debug);
+}
+
+#[test]
+fn shared_reload_after_whitespace_only_edit_allows_later_writes() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let shared = TNT::with_storage(storage.clone()).shared().unwrap();
+
+    storage.write("\n(db) {\n\tport=5432\n}\n\n").unwrap();
+
+    let version = shared.version();
+    assert_eq!(shared.reload().unwrap(), version);
+
+    shared.set("db", "port", 6432).unwrap();
+    assert_eq!(TNT::with_storage(storage).get("db", "port").unwrap(), "6432");
+}
+
+#[test]
+fn save_reports_changes_made_after_load() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+    let mut doc = tnt.load().unwrap();
+
+    storage.write("(db) {\n\tport=6432\n}\n").unwrap();
+    doc.set("db", "host", "localhost").unwrap();
+
+    match tnt.save(&doc) {
+        Err(Error::Conflict(diff)) => assert_eq!(
+            diff.changes(),
+            [Change::VarChanged { key: "db".into(), var: "port".into(), old: "5432".into(), new: "6432".into() }]
+        ),
+        other => panic!("expected a conflict, got {:?}", other),
+    }
+    assert_eq!(storage.read().unwrap(), "(db) {\n\tport=6432\n}\n");
+}
+
+#[test]
+fn save_force_overwrites_foreign_edits() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+    let mut doc = tnt.load().unwrap();
+
+    storage.write("(db) {\n\tport=6432\n}\n").unwrap();
+    doc.set("db", "host", "localhost").unwrap();
+
+    tnt.save_force(&doc).unwrap();
+    assert_eq!(tnt.get("db", "port").unwrap(), "5432");
+    assert_eq!(tnt.get("db", "host").unwrap(), "localhost");
+}
+
+#[test]
+fn saved_document_can_be_saved_again() {
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\tport=5432\n}\n"));
+    let mut doc = tnt.load().unwrap();
+
+    doc.set("db", "port", "6432").unwrap();
+    tnt.save(&doc).unwrap();
+    doc.set("db", "port", "7432").unwrap();
+    tnt.save(&doc).unwrap();
+
+    assert_eq!(tnt.get("db", "port").unwrap(), "7432");
+}
+
+#[test]
+fn whitespace_only_edits_are_not_conflicts() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+    let mut doc = tnt.load().unwrap();
+
+    storage.write("(db) {\n\tport=5432\n}\n\n").unwrap();
+    doc.set("db", "port", "6432").unwrap();
+
+    match tnt.save(&doc) {
+        Err(Error::Conflict(diff)) => assert!(diff.is_empty()),
+        other => panic!("expected a conflict, got {:?}", other),
+    }
+}
+
+#[test]
+fn parsed_documents_are_saved_without_a_check() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+    let doc: Document = "(app) {\n\tname=x\n}\n".parse().unwrap();
+
+    tnt.save(&doc).unwrap();
+    assert_eq!(storage.read().unwrap(), "(app) {\n\tname=x\n}\n");
+}
+
+#[test]
+fn file_edited_after_load_is_a_conflict() {
+    let scratch = Scratch::new("conflict");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::open(&path).unwrap();
+    let mut doc = tnt.load().unwrap();
+
+    std::fs::write(&path, "(db) {\n\tport=5432\n\tpool=10\n}\n").unwrap();
+    doc.set("db", "port", "6432").unwrap();
+
+    match tnt.save(&doc) {
+        Err(Error::Conflict(diff)) => assert_eq!(
+            diff.changes(),
+            [Change::VarAdded { key: "db".into(), var: "pool".into(), val: "10".into() }]
+        ),
+        other => panic!("expected a conflict, got {:?}", other),
+    }
+    assert!(scratch.read("app.tnt").contains("pool=10"));
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use std::process::Command;
+
+use common::Scratch;
+use tnt::{Change, Document};
+
+fn doc(text: &str) -> Document {
+    text.parse().unwrap()
+}
+
+#[test]
+fn changes_cover_sections_vars_and_moves() {
+    let a = doc("(db) {\n\thost=h\n\tport=5432\n\tuser=u\n}\n(old) {\n\tx=1\n}\n(app) {\n\tname=x\n}\n");
+    let b = doc("(db) {\n\tport=6432\n\tpool=10\n}\n(app) {\n\tname=x\n\tuser=u\n}\n(new) {\n\ty=2\n}\n");
+
+    let diff = tnt::diff(&a, &b);
+    let changes = diff.changes();
+
+    assert!(changes.contains(&Change::VarRemoved { key: "db".into(), var: "host".into(), val: "h".into() }));
+    assert!(changes.contains(&Change::VarChanged { key: "db".into(), var: "port".into(), old: "5432".into(), new: "6432".into() }));
+    assert!(changes.contains(&Change::VarAdded { key: "db".into(), var: "pool".into(), val: "10".into() }));
+    assert!(changes.contains(&Change::VarMoved { from: "db".into(), to: "app".into(), var: "user".into(), val: "u".into() }));
+    assert!(changes.contains(&Change::SectionRemoved { key: "old".into(), entries: vec![("x".into(), "1".into())] }));
+    assert!(changes.contains(&Change::SectionAdded { key: "new".into(), entries: vec![("y".into(), "2".into())] }));
+    assert_eq!(changes.len(), 6);
+}
+
+#[test]
+fn equal_documents_have_an_empty_diff() {
+    let a = doc("(db) {\n\tport=5432\n}\n");
+
+    assert!(tnt::diff(&a, &a.clone()).is_empty());
+    assert_eq!(tnt::diff(&a, &a).to_text(), "");
+    assert_eq!(tnt::diff(&a, &a).to_json(), "[]");
+}
+
+#[test]
+fn secrets_are_redacted_unless_asked_for() {
+    let a = doc("(db) {\n\tpassword=!secret hunter1\n}\n");
+    let b = doc("(db) {\n\tpassword=!secret hunter2\n}\n");
+
+    let hidden = Change::VarChanged { key: "db".into(), var: "password".into(), old: "***".into(), new: "***".into() };
+    assert_eq!(tnt::diff(&a, &b).changes(), [hidden]);
+    assert!(!tnt::diff(&a, &b).to_json().contains("hunter"));
+
+    let shown = tnt::diff_with_secrets(&a, &b);
+    assert!(matches!(&shown.changes()[0], Change::VarChanged { new, .. } if new.contains("hunter2")));
+}
+
+#[test]
+fn text_and_json_renderings() {
+    let a = doc("(db) {\n\tport=5432\n}\n");
+    let b = doc("(db) {\n\tport=6432\n}\n");
+    let diff = tnt::diff(&a, &b);
+
+    assert_eq!(diff.to_text(), "section `db`: `port` changed 5432 -> 6432\n");
+    assert_eq!(diff.to_json(), "[\n\t{\"op\": \"var_changed\", \"section\": \"db\", \"var\": \"port\", \"old\": \"5432\", \"new\": \"6432\"}\n]");
+}
+
+#[test]
+fn cli_exit_code_reflects_differences() {
+    let scratch = Scratch::new("diff-cli");
+    let a = scratch.write("a.tnt", "(db) {\n\tport=5432\n}\n");
+    let b = scratch.write("b.tnt", "(db) {\n\tport=6432\n}\n");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_tnt")).arg("diff").arg(&a).arg(&b).output().unwrap();
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "section `db`: `port` changed 5432 -> 6432\n");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_tnt")).args(["diff", "--json"]).arg(&a).arg(&a).output().unwrap();
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "[]\n");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_tnt")).arg("diff").arg(&a).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::process::Command;
+
+use common::Scratch;
+use tnt::{Document, DuplicatePolicy, Error, MemoryStorage, TNT};
+
+const TEXT: &str = "(a) {\n\tx=1\n\tx=2\n}\n(b) {\n\ty=1\n}\n(a) {\n\tx=3\n}\n";
+
+#[test]
+fn delete_key_removes_only_the_block_in_scope() {
+    let first = TNT::with_storage(MemoryStorage::new(TEXT)).duplicates(DuplicatePolicy::FirstWins);
+    first.delete_key("a").unwrap();
+    assert_eq!(first.storage().text(), "(b) {\n\ty=1\n}\n(a) {\n\tx=3\n}\n");
+    assert_eq!(first.get("a", "x").unwrap(), "3");
+
+    let last = TNT::with_storage(MemoryStorage::new(TEXT)).duplicates(DuplicatePolicy::LastWins);
+    last.delete_key("a").unwrap();
+    assert_eq!(last.storage().text(), "(a) {\n\tx=1\n\tx=2\n}\n(b) {\n\ty=1\n}\n");
+    assert_eq!(last.get("a", "x").unwrap(), "2");
+
+    let merge = TNT::with_storage(MemoryStorage::new(TEXT)).duplicates(DuplicatePolicy::Merge);
+    merge.delete_key("a").unwrap();
+    assert_eq!(merge.storage().text(), "(b) {\n\ty=1\n}\n");
+}
+
+#[test]
+fn save_keeps_shadowed_repeats() {
+    for policy in [DuplicatePolicy::FirstWins, DuplicatePolicy::LastWins] {
+        let tnt = TNT::with_storage(MemoryStorage::new(TEXT)).duplicates(policy);
+        let doc = tnt.load().unwrap();
+        let x = doc.get("a", "x").map(str::to_string);
+        assert!(!doc.shadowed().is_empty());
+
+        tnt.save(&doc).unwrap();
+        let text = tnt.storage().text();
+
+        assert!(text.contains("x=1") && text.contains("x=2") && text.contains("x=3"), "{:?}: {}", policy, text);
+        assert_eq!(tnt.load().unwrap().get("a", "x").map(str::to_string), x, "{:?}", policy);
+        assert_eq!(tnt.get("a", "x").unwrap(), x.unwrap(), "{:?}", policy);
+    }
+}
+
+#[test]
+fn shadowed_lists_hidden_blocks_and_vars() {
+    let mut doc: Document = TEXT.parse().unwrap();
+    doc.dedup(DuplicatePolicy::FirstWins).unwrap();
+
+    let hidden: Vec<(&str, Vec<(String, String)>)> = doc.shadowed().iter().map(|s| (s.name(), s.entries().to_vec())).collect();
+    assert_eq!(hidden, [("a", vec![("x".to_string(), "3".to_string())]), ("a", vec![("x".to_string(), "2".to_string())])]);
+
+    let mut doc: Document = TEXT.parse().unwrap();
+    doc.dedup(DuplicatePolicy::Merge).unwrap();
+    assert!(doc.shadowed().is_empty());
+}
+
+#[test]
+fn getters_follow_the_policy() {
+    let tnt = |policy| TNT::with_storage(MemoryStorage::new(TEXT)).duplicates(policy);
+
+    assert_eq!(tnt(DuplicatePolicy::FirstWins).get("a", "x").unwrap(), "1");
+    assert_eq!(tnt(DuplicatePolicy::LastWins).get("a", "x").unwrap(), "3");
+    assert_eq!(tnt(DuplicatePolicy::Merge).get("a", "x").unwrap(), "3");
+    assert_eq!(tnt(DuplicatePolicy::Merge).sections().unwrap(), ["a", "b"]);
+}
+
+#[test]
+fn error_policy_rejects_repeats() {
+    let tnt = TNT::with_storage(MemoryStorage::new(TEXT)).duplicates(DuplicatePolicy::Error);
+
+    assert!(matches!(tnt.load(), Err(Error::VarExists { .. }) | Err(Error::KeyExists(_))));
+    assert!(tnt.validate().is_err());
+
+    let clean = TNT::with_storage(MemoryStorage::new("(a) {\n\tx=1\n}\n")).duplicates(DuplicatePolicy::Error);
+    assert!(clean.validate().is_ok());
+}
+
+#[test]
+fn merge_policy_folds_blocks_into_the_first() {
+    let mut doc: Document = TEXT.parse().unwrap();
+    doc.dedup(DuplicatePolicy::Merge).unwrap();
+
+    assert_eq!(doc.render(), "(a) {\n\tx=3\n}\n(b) {\n\ty=1\n}\n");
+}
+
+#[test]
+fn set_edits_the_effective_occurrence() {
+    let first = TNT::with_storage(MemoryStorage::new(TEXT)).duplicates(DuplicatePolicy::FirstWins);
+    first.set("a", "x", 9).unwrap();
+    assert_eq!(first.storage().text(), "(a) {\n\tx=9\n\tx=2\n}\n(b) {\n\ty=1\n}\n(a) {\n\tx=3\n}\n");
+
+    let last = TNT::with_storage(MemoryStorage::new(TEXT)).duplicates(DuplicatePolicy::LastWins);
+    last.set("a", "x", 9).unwrap();
+    assert_eq!(last.storage().text(), "(a) {\n\tx=1\n\tx=2\n}\n(b) {\n\ty=1\n}\n(a) {\n\tx=9\n}\n");
+}
+
+#[test]
+fn cli_warns_about_ignored_repeats() {
+    let scratch = Scratch::new("duplicates-cli");
+    let a = scratch.write("a.tnt", TEXT);
+
+    let out = Command::new(env!("CARGO_BIN_EXE_tnt")).arg("diff").arg(&a).arg(&a).output().unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+
+    assert_eq!(out.status.code(), Some(0));
+    assert!(stderr.contains("repeated `a.x` is ignored"), "{}", stderr);
+}
@@ -0,0 +1,39 @@
+use tnt::{Error, MemoryStorage, TNT};
+
+fn tnt() -> TNT<MemoryStorage> {
+    TNT::with_storage(MemoryStorage::new("(db) {\n\thost=h\n\tport=5432\n}\n(empty) {\n}\n(app) {\n\tname=x\n}\n"))
+}
+
+#[test]
+fn sections_are_listed_in_file_order() {
+    assert_eq!(tnt().sections().unwrap(), ["db", "empty", "app"]);
+    assert!(TNT::with_storage(MemoryStorage::new("")).sections().unwrap().is_empty());
+}
+
+#[test]
+fn vars_and_entries_of_a_section() {
+    let tnt = tnt();
+
+    assert_eq!(tnt.vars("db").unwrap(), ["host", "port"]);
+    assert_eq!(tnt.entries("db").unwrap(), [("host".to_string(), "h".to_string()), ("port".to_string(), "5432".to_string())]);
+    assert!(tnt.vars("empty").unwrap().is_empty());
+    assert!(tnt.entries("missing").unwrap().is_empty());
+}
+
+#[test]
+fn iter_yields_every_triple_in_file_order() {
+    let all: Vec<(String, String, String)> = tnt().iter().unwrap().collect();
+    let all: Vec<(&str, &str, &str)> = all.iter().map(|(k, v, val)| (k.as_str(), v.as_str(), val.as_str())).collect();
+
+    assert_eq!(all, [("db", "host", "h"), ("db", "port", "5432"), ("app", "name", "x")]);
+}
+
+#[test]
+fn broken_file_is_an_integrity_error() {
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\thost=h\n"));
+
+    assert!(matches!(tnt.sections(), Err(Error::Integrity)));
+    assert!(matches!(tnt.vars("db"), Err(Error::Integrity)));
+    assert!(matches!(tnt.entries("db"), Err(Error::Integrity)));
+    assert!(matches!(tnt.iter(), Err(Error::Integrity)));
+}
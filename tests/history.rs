@@ -0,0 +1,173 @@
+mod common;
+
+use std::fs;
+use std::io;
+use std::process::Command;
+
+use common::Scratch;
+use tnt::{Error, HistoryStorage, TNT, WritableStorage};
+
+const BASE: &str = "(db) {\n\tport=5432\n}\n";
+
+fn history(scratch: &Scratch) -> TNT<HistoryStorage> {
+    TNT::with_storage(HistoryStorage::new(scratch.write("app.tnt", BASE)))
+}
+
+#[test]
+fn every_write_keeps_the_previous_text() {
+    let scratch = Scratch::new("history");
+    let tnt = history(&scratch);
+
+    tnt.set("db", "port", 6432).unwrap();
+    tnt.set("db", "host", "localhost").unwrap();
+
+    let versions = tnt.history().unwrap();
+    assert_eq!(versions.iter().map(|v| v.number()).collect::<Vec<_>>(), [1, 2]);
+    assert!(versions[0].summary().contains("port"), "{}", versions[0].summary());
+    assert!(versions[1].summary().contains("host"), "{}", versions[1].summary());
+
+    assert_eq!(tnt.storage().text(1).unwrap(), BASE);
+    assert_eq!(tnt.show(2).unwrap().get("db", "port"), Some("6432"));
+    assert_eq!(tnt.show(2).unwrap().get("db", "host"), None);
+}
+
+#[test]
+fn unchanged_writes_add_no_version() {
+    let scratch = Scratch::new("history");
+    let tnt = history(&scratch);
+
+    tnt.storage().write(BASE).unwrap();
+
+    assert!(tnt.history().unwrap().is_empty());
+    assert!(!tnt.storage().dir().exists());
+}
+
+#[test]
+fn summaries_hide_secrets() {
+    let scratch = Scratch::new("history");
+    let tnt = TNT::with_storage(HistoryStorage::new(scratch.write("app.tnt", "(db) {\n\tdsn=!secret hunter1\n}\n")));
+
+    tnt.set("db", "dsn", "!secret hunter2").unwrap();
+
+    let summary = tnt.history().unwrap()[0].summary().to_string();
+    assert!(summary.contains("dsn"), "{}", summary);
+    assert!(!summary.contains("hunter"), "{}", summary);
+    assert!(tnt.storage().text(1).unwrap().contains("hunter1"));
+}
+
+#[test]
+fn rollback_restores_a_version_and_can_be_undone() {
+    let scratch = Scratch::new("history");
+    let tnt = history(&scratch);
+
+    tnt.set("db", "port", 6432).unwrap();
+    tnt.rollback(1).unwrap();
+
+    assert_eq!(scratch.read("app.tnt"), BASE);
+    assert_eq!(tnt.history().unwrap().len(), 2);
+
+    tnt.rollback(2).unwrap();
+    assert_eq!(tnt.get("db", "port").unwrap(), "6432");
+}
+
+#[test]
+fn unknown_versions_are_not_found() {
+    let scratch = Scratch::new("history");
+    let tnt = history(&scratch);
+
+    match tnt.rollback(7) {
+        Err(Error::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::NotFound),
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+    assert!(tnt.show(7).is_err());
+    assert_eq!(scratch.read("app.tnt"), BASE);
+}
+
+#[test]
+fn keep_prunes_the_oldest_versions() {
+    let scratch = Scratch::new("history");
+    let tnt = TNT::with_storage(HistoryStorage::new(scratch.write("app.tnt", BASE)).keep(2));
+
+    for port in 1..=5 {
+        tnt.set("db", "port", port).unwrap();
+    }
+
+    let numbers: Vec<u64> = tnt.history().unwrap().iter().map(|v| v.number()).collect();
+    assert_eq!(numbers, [4, 5]);
+    assert_eq!(tnt.show(5).unwrap().get("db", "port"), Some("4"));
+    assert!(tnt.storage().text(3).is_err());
+}
+
+#[test]
+fn keep_zero_means_unlimited() {
+    let scratch = Scratch::new("history");
+    let tnt = TNT::with_storage(HistoryStorage::new(scratch.write("app.tnt", BASE)).keep(0));
+
+    tnt.set("db", "port", 1).unwrap();
+    tnt.set("db", "port", 2).unwrap();
+
+    assert_eq!(tnt.history().unwrap().len(), 2);
+    assert_eq!(tnt.storage().text(2).unwrap(), "(db) {\n\tport=1\n}\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn versions_keep_the_file_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let scratch = Scratch::new("history-mode");
+    let path = scratch.write("app.tnt", "(db) {\n\tpassword=hunter2\n}\n");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let tnt = TNT::with_storage(HistoryStorage::new(&path));
+    tnt.set("db", "password", "hunter3").unwrap();
+
+    for file in ["000001.tnt", "000001.info"] {
+        let mode = fs::metadata(tnt.storage().dir().join(file)).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "{}", file);
+    }
+}
+
+#[test]
+fn numbering_continues_after_pruning() {
+    let scratch = Scratch::new("history");
+    let path = scratch.write("app.tnt", BASE);
+    let tnt = TNT::with_storage(HistoryStorage::new(&path).keep(1));
+
+    tnt.set("db", "port", 1).unwrap();
+    tnt.set("db", "port", 2).unwrap();
+
+    let reopened = TNT::with_storage(HistoryStorage::new(&path));
+    reopened.set("db", "port", 3).unwrap();
+
+    let numbers: Vec<u64> = reopened.history().unwrap().iter().map(|v| v.number()).collect();
+    assert_eq!(numbers, [2, 3]);
+}
+
+fn cli(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_tnt")).args(args).output().unwrap()
+}
+
+#[test]
+fn cli_lists_shows_and_rolls_back() {
+    let scratch = Scratch::new("history");
+    let tnt = history(&scratch);
+    tnt.set("db", "port", 6432).unwrap();
+    let file = scratch.path("app.tnt").to_string_lossy().into_owned();
+
+    let listed = cli(&["history", &file]);
+    assert!(listed.status.success());
+    let listed = String::from_utf8(listed.stdout).unwrap();
+    assert!(listed.starts_with("1\t"), "{}", listed);
+    assert!(listed.contains("port"), "{}", listed);
+
+    let shown = cli(&["show", &file, "1"]);
+    assert!(shown.status.success());
+    assert_eq!(String::from_utf8(shown.stdout).unwrap(), BASE);
+
+    assert!(!cli(&["show", &file, "one"]).status.success());
+    assert_eq!(cli(&["rollback", &file]).status.code(), Some(2));
+
+    assert!(cli(&["rollback", &file, "1"]).status.success());
+    assert_eq!(scratch.read("app.tnt"), BASE);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::Scratch;
+use tnt::{DuplicatePolicy, Error, TNT};
+
+#[test]
+fn diamond_include_reads_shared_file_once() {
+    let scratch = Scratch::new("include-diamond");
+    scratch.write("d.tnt", "(shared) {\n\tx=d\n}\n");
+    scratch.write("b.tnt", "@include d.tnt\n(b) {\n\ty=b\n}\n");
+    scratch.write("c.tnt", "@include d.tnt\n(c) {\n\tz=c\n}\n");
+    let a = scratch.write("a.tnt", "@include b.tnt\n@include c.tnt\n(a) {\n\tw=a\n}\n");
+
+    let composed = TNT::open(&a).unwrap().duplicates(DuplicatePolicy::Error).compose().unwrap();
+
+    let names: Vec<String> =
+        composed.files().iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+    assert_eq!(names, ["d.tnt", "b.tnt", "c.tnt", "a.tnt"]);
+    assert_eq!(composed.get("shared", "x"), Some("d"));
+}
+
+fn names(composed: &tnt::Composed) -> Vec<String> {
+    composed.files().iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect()
+}
+
+#[test]
+fn later_files_override_earlier_ones() {
+    let scratch = Scratch::new("include-override");
+    scratch.write("base.tnt", "(db) {\n\thost=base\n\tport=1\n\tpool=5\n}\n");
+    scratch.write("local.tnt", "(db) {\n\tport=2\n}\n");
+    let main = scratch.write("app.tnt", "(db) {\n\thost=main\n}\n@include base.tnt\n@include local.tnt\n");
+
+    let composed = TNT::open(&main).unwrap().compose().unwrap();
+
+    assert_eq!(names(&composed), ["base.tnt", "local.tnt", "app.tnt"]);
+    assert_eq!(composed.get("db", "host"), Some("main"));
+    assert_eq!(composed.get("db", "port"), Some("2"));
+    assert_eq!(composed.get("db", "pool"), Some("5"));
+    assert_eq!(composed.origin("db", "port"), Some(scratch.path("local.tnt").as_path()));
+}
+
+#[test]
+fn globs_match_in_alphabetical_order() {
+    let scratch = Scratch::new("include-glob");
+    std::fs::create_dir(scratch.path("conf.d")).unwrap();
+    scratch.write("conf.d/20-b.tnt", "(a) {\n\tx=b\n}\n");
+    scratch.write("conf.d/10-a.tnt", "(a) {\n\tx=a\n}\n");
+    scratch.write("conf.d/notes.txt", "(a) {\n\tx=txt\n}\n");
+    let main = scratch.write("app.tnt", "@include conf.d/*.tnt\n");
+
+    let composed = TNT::open(&main).unwrap().compose().unwrap();
+
+    assert_eq!(names(&composed), ["10-a.tnt", "20-b.tnt", "app.tnt"]);
+    assert_eq!(composed.get("a", "x"), Some("b"));
+}
+
+#[test]
+fn include_cycle_is_an_error() {
+    let scratch = Scratch::new("include-cycle");
+    scratch.write("b.tnt", "@include a.tnt\n");
+    let a = scratch.write("a.tnt", "@include b.tnt\n");
+
+    assert!(matches!(TNT::open(&a).unwrap().compose(), Err(Error::IncludeCycle(cycle)) if cycle.len() == 3));
+}
+
+#[test]
+fn writes_go_to_the_defining_file() {
+    let scratch = Scratch::new("include-write");
+    scratch.write("base.tnt", "(db) {\n\tport=1\n\thost=h\n}\n");
+    let main = scratch.write("app.tnt", "@include \"base.tnt\"\n(db) {\n\thost=main\n}\n");
+
+    let mut composed = TNT::open(&main).unwrap().compose().unwrap();
+    composed.set("db", "port", 2).unwrap();
+    composed.set("db", "pool", 10).unwrap();
+
+    assert_eq!(scratch.read("base.tnt"), "(db) {\n\tport=2\n\thost=h\n}\n");
+    assert_eq!(scratch.read("app.tnt"), "@include \"base.tnt\"\n(db) {\n\thost=main\n\tpool=10\n}\n");
+
+    composed.delete_var("db", "host").unwrap();
+    assert_eq!(composed.get("db", "host"), Some("h"));
+    assert!(matches!(composed.delete_var("db", "missing"), Err(Error::VarNotFound { .. })));
+}
+
+#[test]
+fn plain_reads_do_not_follow_includes() {
+    let scratch = Scratch::new("include-plain");
+    scratch.write("base.tnt", "(db) {\n\tport=1\n}\n");
+    let main = scratch.write("app.tnt", "@include base.tnt\n(app) {\n\tname=x\n}\n");
+
+    let tnt = TNT::open(&main).unwrap();
+
+    assert_eq!(tnt.get("db", "port").unwrap(), "NONE_VAL");
+    assert_eq!(tnt.sections().unwrap(), ["app"]);
+}
+
+#[test]
+fn writes_use_the_duplicate_policy_of_compose() {
+    let scratch = Scratch::new("include-policy");
+    scratch.write("base.tnt", "(db) {\n\tport=1\n}\n");
+    let main = scratch.write("app.tnt", "@include \"base.tnt\"\n(app) {\n\tname=x\n}\n");
+
+    let mut composed = TNT::open(&main).unwrap().duplicates(DuplicatePolicy::Merge).compose().unwrap();
+    scratch.write("base.tnt", "(db) {\n\tport=1\n}\n(db) {\n\thost=h\n}\n");
+
+    // Under Merge the second block is part of the section, so the conflict shows it.
+    let err = composed.set("db", "port", 2).unwrap_err();
+    assert!(matches!(err, Error::Conflict(ref diff) if !diff.is_empty()), "{:?}", err);
+}
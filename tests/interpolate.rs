@@ -0,0 +1,71 @@
+use std::io;
+
+use tnt::{Document, Error, MemoryStorage, TNT};
+
+fn doc(text: &str) -> Document {
+    text.parse().unwrap()
+}
+
+#[test]
+fn references_are_resolved_recursively() {
+    let doc = doc("(db) {\n\thost=h\n\tport=5432\n\taddr=${db.host}:${db.port}\n}\n(app) {\n\turl=pg://${db.addr}/app\n}\n");
+
+    assert_eq!(doc.resolve("app", "url").unwrap().as_deref(), Some("pg://h:5432/app"));
+    assert_eq!(doc.get("app", "url"), Some("pg://${db.addr}/app"));
+    assert_eq!(doc.resolve("app", "missing").unwrap(), None);
+}
+
+#[test]
+fn escapes_and_lone_dollars_are_literal() {
+    let doc = doc("(a) {\n\tprice=$5\n\ttemplate=$${a.price}\n}\n");
+
+    assert_eq!(doc.resolve("a", "price").unwrap().as_deref(), Some("$5"));
+    assert_eq!(doc.resolve("a", "template").unwrap().as_deref(), Some("${a.price}"));
+}
+
+#[test]
+fn environment_references() {
+    let path = std::env::var("PATH").unwrap();
+    let doc = doc("(a) {\n\tpath=${env:PATH}\n\tmissing=${env:TNT_TEST_SURELY_UNSET_VARIABLE}\n}\n");
+
+    assert_eq!(doc.resolve("a", "path").unwrap(), Some(path));
+    assert!(matches!(doc.resolve("a", "missing"), Err(Error::Unresolved(r)) if r == "${env:TNT_TEST_SURELY_UNSET_VARIABLE}"));
+}
+
+#[test]
+fn cycles_are_reported_with_their_path() {
+    let doc = doc("(a) {\n\tx=${a.y}\n\ty=${b.z}\n}\n(b) {\n\tz=${a.x}\n}\n");
+
+    let Err(Error::Cycle(cycle)) = doc.resolve("a", "x") else { panic!("expected a cycle") };
+    assert_eq!(cycle, ["a.x", "a.y", "b.z", "a.x"]);
+}
+
+#[test]
+fn unknown_and_malformed_references_are_unresolved() {
+    let mut doc = doc("(a) {\n\tx=${a.missing}\n\ty=${nodot}\n}\n");
+
+    assert!(matches!(doc.resolve("a", "x"), Err(Error::Unresolved(r)) if r == "${a.missing}"));
+    assert!(matches!(doc.resolve("a", "y"), Err(Error::Unresolved(_))));
+    assert!(matches!(doc.set("a", "z", "cost ${a"), Err(Error::Parse(_))));
+}
+
+#[test]
+fn tnt_get_resolves_and_get_raw_does_not() {
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\thost=h\n\turl=pg://${db.host}\n\tbad=${db.nope}\n}\n"));
+
+    assert_eq!(tnt.get("db", "url").unwrap(), "pg://h");
+    assert_eq!(tnt.get_raw("db", "url").unwrap(), "pg://${db.host}");
+
+    let err = tnt.get("db", "bad").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn resolved_copies_every_value() {
+    let doc = doc("(db) {\n\thost=h\n\turl=pg://${db.host}\n}\n");
+    let resolved = doc.resolved().unwrap();
+
+    assert_eq!(resolved.get("db", "url"), Some("pg://h"));
+    assert_eq!(resolved.get("db", "host"), Some("h"));
+    assert!(!resolved.is_secret("db", "url"));
+}
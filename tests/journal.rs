@@ -0,0 +1,187 @@
+mod common;
+
+use std::fs::{self, OpenOptions as FileOptions};
+use std::io::{self, Write};
+
+use common::Scratch;
+use tnt::{JournalStorage, OpenOptions, TNT};
+
+#[test]
+fn changes_are_appended_and_replayed() {
+    let scratch = Scratch::new("journal-replay");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let tnt = OpenOptions::new().open_journaled(&path).unwrap();
+    tnt.set("db", "port", 6432).unwrap();
+    tnt.set("db", "host", "localhost").unwrap();
+
+    assert_eq!(scratch.read("app.tnt"), "(db) {\n\tport=5432\n}\n");
+    assert_eq!(tnt.storage().pending().unwrap(), 2);
+
+    let reopened = TNT::with_storage(JournalStorage::new(&path));
+    assert_eq!(reopened.get("db", "port").unwrap(), "6432");
+    assert_eq!(reopened.get("db", "host").unwrap(), "localhost");
+}
+
+#[test]
+fn torn_last_record_is_dropped_and_overwritten() {
+    let scratch = Scratch::new("journal-torn");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let tnt = OpenOptions::new().open_journaled(&path).unwrap();
+    tnt.set("db", "port", 6432).unwrap();
+
+    let journal = tnt.storage().journal_filename().to_string();
+    FileOptions::new().append(true).open(&journal).unwrap().write_all(b"0123456789abcdef\t[{\"op\": \"se").unwrap();
+
+    let reopened = TNT::with_storage(JournalStorage::new(&path));
+    assert_eq!(reopened.get("db", "port").unwrap(), "6432");
+    assert_eq!(reopened.storage().pending().unwrap(), 1);
+
+    reopened.set("db", "port", 7432).unwrap();
+    assert_eq!(reopened.storage().pending().unwrap(), 2);
+    assert!(!fs::read_to_string(&journal).unwrap().contains("\"se\n"));
+    assert_eq!(TNT::with_storage(JournalStorage::new(&path)).get("db", "port").unwrap(), "7432");
+}
+
+#[test]
+fn corrupt_record_in_the_middle_is_an_error() {
+    let scratch = Scratch::new("journal-corrupt");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let tnt = OpenOptions::new().open_journaled(&path).unwrap();
+    tnt.set("db", "port", 6432).unwrap();
+    tnt.set("db", "port", 7432).unwrap();
+
+    let journal = tnt.storage().journal_filename().to_string();
+    let text = fs::read_to_string(&journal).unwrap();
+    let mut lines: Vec<&str> = text.lines().collect();
+    lines[1] = "0000000000000000\t[]";
+    fs::write(&journal, lines.join("\n") + "\n").unwrap();
+
+    let err = JournalStorage::new(&path).pending().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn journal_for_another_base_is_an_error() {
+    let scratch = Scratch::new("journal-stale");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let tnt = OpenOptions::new().open_journaled(&path).unwrap();
+    tnt.set("db", "port", 6432).unwrap();
+
+    scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n(app) {\n\tname=x\n}\n");
+
+    let reopened = TNT::with_storage(JournalStorage::new(&path));
+    assert_eq!(reopened.get("db", "port").unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert!(reopened.set("db", "port", 1).is_err());
+    assert!(fs::read_to_string(reopened.storage().journal_filename()).unwrap().contains("6432"));
+}
+
+#[test]
+fn journal_left_by_an_interrupted_compact_is_ignored() {
+    let scratch = Scratch::new("journal-folded");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let tnt = OpenOptions::new().open_journaled(&path).unwrap();
+    tnt.set("db", "port", 6432).unwrap();
+
+    // Stop the compact after the main file is written, before the journal is removed.
+    let journal = tnt.storage().journal_filename().to_string();
+    let saved = fs::read_to_string(&journal).unwrap();
+    tnt.compact().unwrap();
+    let folded = saved + &format!("@folded {:016x}\n", fnv1a(&scratch.read("app.tnt")));
+    fs::write(&journal, folded).unwrap();
+
+    let reopened = TNT::with_storage(JournalStorage::new(&path));
+    assert_eq!(reopened.get("db", "port").unwrap(), "6432");
+    assert_eq!(reopened.storage().pending().unwrap(), 0);
+
+    reopened.set("db", "host", "h").unwrap();
+    assert_eq!(reopened.storage().pending().unwrap(), 1);
+    assert_eq!(TNT::with_storage(JournalStorage::new(&path)).get("db", "port").unwrap(), "6432");
+}
+
+#[cfg(unix)]
+#[test]
+fn journal_keeps_the_file_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let scratch = Scratch::new("journal-mode");
+    let path = scratch.write("app.tnt", "(db) {\n\tpassword=hunter2\n}\n");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let tnt = TNT::with_storage(JournalStorage::new(&path));
+    tnt.set("db", "password", "hunter3").unwrap();
+
+    let mode = fs::metadata(tnt.storage().journal_filename()).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+/// The journal's checksum, FNV-1a.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[test]
+fn compact_folds_journal_into_the_file() {
+    let scratch = Scratch::new("journal-compact");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let tnt = OpenOptions::new().open_journaled(&path).unwrap();
+    tnt.set("db", "port", 6432).unwrap();
+    tnt.compact().unwrap();
+
+    assert_eq!(scratch.read("app.tnt"), "(db) {\n\tport=6432\n}\n");
+    assert!(!fs::exists(tnt.storage().journal_filename()).unwrap());
+    assert_eq!(tnt.storage().pending().unwrap(), 0);
+}
+
+#[test]
+fn compact_after_folds_journal_at_the_threshold() {
+    let scratch = Scratch::new("journal-threshold");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let tnt = TNT::with_storage(JournalStorage::new(&path).compact_after(3));
+    tnt.set("db", "port", 1).unwrap();
+    tnt.set("db", "port", 2).unwrap();
+    assert_eq!(tnt.storage().pending().unwrap(), 2);
+
+    tnt.set("db", "port", 3).unwrap();
+    assert_eq!(tnt.storage().pending().unwrap(), 0);
+    assert_eq!(scratch.read("app.tnt"), "(db) {\n\tport=3\n}\n");
+
+    tnt.set("db", "port", 4).unwrap();
+    assert_eq!(tnt.storage().pending().unwrap(), 1);
+    assert_eq!(tnt.get("db", "port").unwrap(), "4");
+}
+
+#[test]
+fn cached_state_sees_appends_from_another_handle() {
+    let scratch = Scratch::new("journal-cache");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let first = TNT::with_storage(JournalStorage::new(&path));
+    let second = TNT::with_storage(JournalStorage::new(&path));
+    assert_eq!(first.get("db", "port").unwrap(), "5432");
+
+    second.set("db", "port", 6432).unwrap();
+    assert_eq!(first.get("db", "port").unwrap(), "6432");
+
+    first.set("db", "host", "h").unwrap();
+    assert_eq!(second.get("db", "port").unwrap(), "6432");
+    assert_eq!(second.get("db", "host").unwrap(), "h");
+    assert_eq!(second.storage().pending().unwrap(), 2);
+}
+
+#[test]
+fn debug_does_not_print_cached_text() {
+    let scratch = Scratch::new("journal-debug");
+    let path = scratch.write("app.tnt", "(db) {\n\tpassword=!secret hunter2\n}\n");
+
+    let tnt = TNT::with_storage(JournalStorage::new(&path));
+    tnt.get("db", "password").unwrap();
+
+    assert!(!format!("{:?}", tnt).contains("hunter2"));
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::Scratch;
+use tnt::{Document, Error, MemoryStorage, TNT};
+
+#[test]
+fn lists_parse_plain_quoted_and_empty_values() {
+    let doc: Document = "(a) {\n\thosts=[db1, db2 , db3]\n\tquoted=[\"a, b\", \" c \", d]\n\tempty=[]\n\tplain=solo\n}\n".parse().unwrap();
+
+    assert_eq!(doc.get_list("a", "hosts").unwrap(), ["db1", "db2", "db3"]);
+    assert_eq!(doc.get_list("a", "quoted").unwrap(), ["a, b", " c ", "d"]);
+    assert!(doc.get_list("a", "empty").unwrap().is_empty());
+    assert_eq!(doc.get_list("a", "plain").unwrap(), ["solo"]);
+    assert_eq!(doc.get_list("a", "missing"), None);
+}
+
+#[test]
+fn push_remove_and_insert_rewrite_the_list() {
+    let storage = MemoryStorage::new("(cluster) {\n\thosts=[db1, db2]\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.push("cluster", "hosts", "db3").unwrap();
+    tnt.insert_at("cluster", "hosts", 0, "db0").unwrap();
+    assert!(tnt.remove_item("cluster", "hosts", "db2").unwrap());
+    assert!(!tnt.remove_item("cluster", "hosts", "db9").unwrap());
+
+    assert_eq!(storage.text(), "(cluster) {\n\thosts=[db0, db1, db3]\n}\n");
+    assert_eq!(tnt.get_list("cluster", "hosts").unwrap(), ["db0", "db1", "db3"]);
+}
+
+#[test]
+fn push_creates_the_variable() {
+    let storage = MemoryStorage::new("");
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.push("cluster", "hosts", "a, b").unwrap();
+
+    assert_eq!(storage.text(), "(cluster) {\n\thosts=[\"a, b\"]\n}\n");
+    assert_eq!(tnt.get_list("cluster", "hosts").unwrap(), ["a, b"]);
+}
+
+#[test]
+fn insert_past_the_end_is_an_error() {
+    let tnt = TNT::with_storage(MemoryStorage::new("(a) {\n\tx=[1, 2]\n}\n"));
+
+    let err = tnt.insert_at("a", "x", 3, "z").unwrap_err();
+    assert!(matches!(err, Error::IndexOutOfRange { index: 3, len: 2, .. }));
+    assert!(matches!(tnt.get_list("a", "missing"), Err(Error::VarNotFound { .. })));
+}
+
+#[test]
+fn lists_export_as_arrays() {
+    let scratch = Scratch::new("list-export");
+    let tnt = TNT::with_storage(MemoryStorage::new("(a) {\n\thosts=[db1, \"x\\\"y\"]\n}\n"));
+
+    tnt.to_json(&scratch.stem("out")).unwrap();
+    tnt.to_toml(&scratch.stem("out")).unwrap();
+
+    assert!(scratch.read("out.json").contains("\"hosts\": [\"db1\", \"x\\\"y\"]"), "{}", scratch.read("out.json"));
+    assert!(scratch.read("out.toml").contains("hosts=[\"db1\", \"x\\\"y\"]"), "{}", scratch.read("out.toml"));
+}
+
+#[test]
+fn exports_keep_values_with_quotes_equals_and_brackets() {
+    let scratch = Scratch::new("list-export-escape");
+    let tnt = TNT::with_storage(MemoryStorage::new(
+        "(a) {\n\tquote=say \"hi\" \\ bye\n\turl=postgres://h/db?sslmode=require\n\tnote=f(x) = 1\n\thosts=[db1, db2]\n}\n(b) {\n\tx=1\n}\n",
+    ));
+
+    tnt.to_json(&scratch.stem("out")).unwrap();
+    tnt.to_toml(&scratch.stem("out")).unwrap();
+
+    for table in [
+        serde_json::from_str::<serde_json::Value>(&scratch.read("out.json")).unwrap(),
+        serde_json::to_value(scratch.read("out.toml").parse::<toml::Table>().unwrap()).unwrap(),
+    ] {
+        assert_eq!(table["a"]["quote"], "say \"hi\" \\ bye");
+        assert_eq!(table["a"]["url"], "postgres://h/db?sslmode=require");
+        assert_eq!(table["a"]["note"], "f(x) = 1");
+        assert_eq!(table["a"]["hosts"], serde_json::json!(["db1", "db2"]));
+        assert_eq!(table["b"]["x"], "1");
+    }
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use std::process::Command;
+
+use common::Scratch;
+use tnt::{Conflict, Document};
+
+fn doc(text: &str) -> Document {
+    text.parse().unwrap()
+}
+
+#[test]
+fn independent_changes_merge_cleanly() {
+    let base = doc("(db) {\n\thost=h\n\tport=5432\n}\n");
+    let ours = doc("(db) {\n\thost=h\n\tport=6432\n}\n");
+    let theirs = doc("(db) {\n\thost=db.local\n\tport=5432\n\tpool=10\n}\n(app) {\n\tname=x\n}\n");
+
+    let merged = tnt::merge(&base, &ours, &theirs);
+
+    assert!(merged.is_clean());
+    assert_eq!(merged.render(), "(db) {\n\thost=db.local\n\tport=6432\n\tpool=10\n}\n(app) {\n\tname=x\n}\n");
+}
+
+#[test]
+fn conflicting_changes_keep_ours_and_are_reported() {
+    let base = doc("(db) {\n\tport=5432\n}\n");
+    let ours = doc("(db) {\n\tport=6432\n}\n");
+    let theirs = doc("(db) {\n\tport=7432\n}\n");
+
+    let merged = tnt::merge(&base, &ours, &theirs);
+
+    assert_eq!(
+        merged.conflicts(),
+        [Conflict {
+            key: "db".into(),
+            var: "port".into(),
+            base: Some("5432".into()),
+            ours: Some("6432".into()),
+            theirs: Some("7432".into()),
+        }]
+    );
+    assert_eq!(merged.document().get("db", "port"), Some("6432"));
+    assert_eq!(merged.render(), "(db) {\n<<<<<<< ours\n\tport=6432\n=======\n\tport=7432\n>>>>>>> theirs\n}\n");
+}
+
+#[test]
+fn delete_against_edit_is_a_conflict() {
+    let base = doc("(db) {\n\tport=5432\n}\n");
+    let ours = doc("(db) {\n}\n");
+    let theirs = doc("(db) {\n\tport=7432\n}\n");
+
+    let merged = tnt::merge(&base, &ours, &theirs);
+
+    assert_eq!(merged.conflicts().len(), 1);
+    assert_eq!(merged.conflicts()[0].ours, None);
+    assert_eq!(merged.document().get("db", "port"), Some("7432"));
+}
+
+#[test]
+fn deleted_section_stays_deleted_unless_something_is_left() {
+    let base = doc("(old) {\n\tx=1\n}\n(gone) {\n\ty=1\n}\n");
+    let ours = doc("(gone) {\n\ty=1\n}\n");
+    let theirs = doc("(old) {\n\tx=1\n}\n");
+
+    let merged = tnt::merge(&base, &ours, &theirs);
+
+    assert!(merged.is_clean());
+    assert!(merged.document().sections().is_empty());
+}
+
+#[test]
+fn driver_writes_ours_and_fails_on_conflict() {
+    let scratch = Scratch::new("merge-driver");
+    let base = scratch.write("base.tnt", "(db) {\n\tport=5432\n\thost=h\n}\n");
+    let ours = scratch.write("ours.tnt", "(db) {\n\tport=6432\n\thost=h\n}\n");
+    let theirs = scratch.write("theirs.tnt", "(db) {\n\tport=7432\n\thost=db.local\n}\n");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_tnt")).args(["merge", "--driver"]).arg(&base).arg(&ours).arg(&theirs).output().unwrap();
+
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&out.stderr), "conflict: section `db`: `port`\n");
+    assert_eq!(
+        scratch.read("ours.tnt"),
+        "(db) {\n<<<<<<< ours\n\tport=6432\n=======\n\tport=7432\n>>>>>>> theirs\n\thost=db.local\n}\n"
+    );
+}
+
+#[test]
+fn driver_succeeds_on_a_clean_merge() {
+    let scratch = Scratch::new("merge-clean");
+    let base = scratch.write("base.tnt", "(db) {\n\tport=5432\n}\n");
+    let ours = scratch.write("ours.tnt", "(db) {\n\tport=6432\n}\n");
+    let theirs = scratch.write("theirs.tnt", "(db) {\n\tport=5432\n\tpool=10\n}\n");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_tnt")).arg("merge").arg(&base).arg(&ours).arg(&theirs).output().unwrap();
+
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "(db) {\n\tport=6432\n\tpool=10\n}\n");
+    assert_eq!(scratch.read("ours.tnt"), "(db) {\n\tport=6432\n}\n");
+}
+
+#[test]
+fn deleted_section_against_added_variable_is_a_conflict() {
+    let base = doc("(db) {\n\tport=5432\n}\n");
+    let ours = doc("");
+    let theirs = doc("(db) {\n\tport=5432\n\tpool=10\n}\n");
+
+    let merged = tnt::merge(&base, &ours, &theirs);
+
+    assert_eq!(
+        merged.conflicts(),
+        [Conflict { key: "db".into(), var: "pool".into(), base: None, ours: None, theirs: Some("10".into()) }]
+    );
+    assert_eq!(merged.document().get("db", "pool"), Some("10"));
+    assert_eq!(merged.document().get("db", "port"), None);
+
+    let merged = tnt::merge(&base, &theirs, &ours);
+
+    assert_eq!(merged.conflicts().len(), 1);
+    assert_eq!(merged.conflicts()[0].ours, Some("10".into()));
+}
+
+#[test]
+fn includes_are_merged_three_way() {
+    let base = doc("@include \"a.tnt\"\n@include \"b.tnt\"\n");
+    let ours = doc("@include \"b.tnt\"\n@include \"c.tnt\"\n");
+    let theirs = doc("@include \"a.tnt\"\n@include \"b.tnt\"\n@include \"d.tnt\"\n");
+
+    let merged = tnt::merge(&base, &ours, &theirs);
+
+    assert_eq!(merged.document().includes(), ["b.tnt", "c.tnt", "d.tnt"]);
+}
@@ -0,0 +1,90 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use tnt::{Document, Error, MemoryStorage, Migrator, TNT};
+
+fn migrator() -> Migrator {
+    Migrator::new()
+        .step(1, "rename db section", |doc| doc.rename_section("db", "database"))
+        .step(2, "default pool size", |doc| {
+            doc.set("database", "pool", 10).unwrap();
+            Ok(())
+        })
+}
+
+#[test]
+fn migrate_applies_pending_steps_and_stamps_the_version() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    let report = migrator().migrate(&tnt).unwrap();
+
+    assert_eq!(report.applied(), [(1, "rename db section".to_string()), (2, "default pool size".to_string())]);
+    assert_eq!(storage.text(), "@version 2\n(database) {\n\tport=5432\n\tpool=10\n}\n");
+    assert_eq!(report.before().version(), None);
+    assert_eq!(report.after().version(), Some(2));
+    assert!(!report.diff().is_empty());
+}
+
+#[test]
+fn migrate_is_idempotent() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    migrator().migrate(&tnt).unwrap();
+    let text = storage.text();
+    let report = migrator().migrate(&tnt).unwrap();
+
+    assert!(report.applied().is_empty());
+    assert!(report.diff().is_empty());
+    assert_eq!(storage.text(), text);
+}
+
+#[test]
+fn only_steps_above_the_version_run() {
+    let doc: Document = "@version 1\n(database) {\n}\n".parse().unwrap();
+    assert_eq!(migrator().pending(&doc), [2]);
+
+    let mut doc = doc;
+    let applied = migrator().apply(&mut doc).unwrap();
+    assert_eq!(applied, [(2, "default pool size".to_string())]);
+    assert_eq!(doc.get("database", "pool"), Some("10"));
+}
+
+#[test]
+fn failed_step_leaves_the_document_unchanged() {
+    let ran = Rc::new(Cell::new(false));
+    let seen = Rc::clone(&ran);
+    let migrator = Migrator::new()
+        .step(1, "set", move |doc| {
+            seen.set(true);
+            doc.set("a", "x", 1).unwrap();
+            Ok(())
+        })
+        .step(2, "fail", |doc| doc.rename_section("missing", "b"));
+
+    let mut doc: Document = "(a) {\n}\n".parse().unwrap();
+    let original = doc.clone();
+
+    assert!(matches!(migrator.apply(&mut doc), Err(Error::KeyNotFound(_))));
+    assert!(ran.get());
+    assert_eq!(doc, original);
+}
+
+#[test]
+fn dry_run_does_not_write() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    let report = migrator().dry_run(&tnt).unwrap();
+
+    assert_eq!(report.applied().len(), 2);
+    assert_eq!(report.after().get("database", "pool"), Some("10"));
+    assert_eq!(storage.text(), "(db) {\n\tport=5432\n}\n");
+}
+
+#[test]
+#[should_panic(expected = "strictly increasing")]
+fn step_versions_must_increase() {
+    let _ = Migrator::new().step(2, "a", |_| Ok(())).step(2, "b", |_| Ok(()));
+}
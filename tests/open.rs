@@ -0,0 +1,77 @@
+mod common;
+
+use std::io::ErrorKind;
+
+use common::Scratch;
+use tnt::{DuplicatePolicy, Error, OpenOptions, TNT};
+
+fn kind(err: Error) -> ErrorKind {
+    match err {
+        Error::Io(err) => err.kind(),
+        other => panic!("expected an I/O error, got {:?}", other),
+    }
+}
+
+#[test]
+fn missing_file_is_an_error_by_default() {
+    let scratch = Scratch::new("open-missing");
+    let path = scratch.path("typo.tnt");
+
+    let err = TNT::open(&path).unwrap_err();
+    assert!(err.to_string().contains("typo.tnt"), "{}", err);
+    assert_eq!(kind(err), ErrorKind::NotFound);
+    assert_eq!(kind(TNT::open_read_only(&path).unwrap_err()), ErrorKind::NotFound);
+    assert!(!path.exists());
+}
+
+#[test]
+fn directories_are_rejected() {
+    let scratch = Scratch::new("open-dir");
+
+    assert_eq!(kind(TNT::open(scratch.dir()).unwrap_err()), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn create_makes_an_empty_file_and_keeps_an_existing_one() {
+    let scratch = Scratch::new("open-create");
+
+    let tnt = OpenOptions::new().create().open(scratch.path("new.tnt")).unwrap();
+    assert_eq!(scratch.read("new.tnt"), "");
+    tnt.set("db", "port", 5432).unwrap();
+
+    OpenOptions::new().create().open(scratch.path("new.tnt")).unwrap();
+    assert_eq!(scratch.read("new.tnt"), "(db) {\n\tport=5432\n}\n");
+}
+
+#[test]
+fn create_new_refuses_an_existing_file() {
+    let scratch = Scratch::new("open-create-new");
+    let path = scratch.path("app.tnt");
+
+    OpenOptions::new().create_new().open(&path).unwrap();
+    assert_eq!(kind(OpenOptions::new().create_new().open(&path).unwrap_err()), ErrorKind::AlreadyExists);
+    OpenOptions::new().create_new().must_exist().open(&path).unwrap();
+}
+
+#[test]
+fn options_carry_the_duplicate_policy() {
+    let scratch = Scratch::new("open-duplicates");
+    let path = scratch.write("app.tnt", "(a) {\n\tx=1\n\tx=2\n}\n");
+
+    let tnt = OpenOptions::new().duplicates(DuplicatePolicy::LastWins).open_read_only(&path).unwrap();
+
+    assert_eq!(tnt.duplicate_policy(), DuplicatePolicy::LastWins);
+    assert_eq!(tnt.get("a", "x").unwrap(), "2");
+}
+
+#[test]
+#[allow(deprecated)]
+fn connect_still_creates_missing_files() {
+    let scratch = Scratch::new("open-connect");
+    let path = scratch.stem("legacy.tnt");
+
+    let tnt = TNT::connect(&path);
+
+    assert_eq!(tnt.filename(), path);
+    assert_eq!(scratch.read("legacy.tnt"), "");
+}
@@ -0,0 +1,54 @@
+use std::io::Cursor;
+
+use tnt::{Document, Error};
+
+#[test]
+fn parse_and_render_round_trip() {
+    let text = "@version 3\n@include \"base.tnt\"\n(db) {\n\thost=h\n\tpassword=!secret hunter2\n}\n(empty) {\n}\n";
+    let doc = Document::parse(text).unwrap();
+
+    assert_eq!(doc.version(), Some(3));
+    assert_eq!(doc.includes(), ["base.tnt"]);
+    assert_eq!(doc.get("db", "password"), Some("hunter2"));
+    assert_eq!(doc.render(), text);
+    assert_eq!(Document::parse(&doc.render()).unwrap(), doc);
+}
+
+#[test]
+fn from_str_and_display_match_parse_and_render() {
+    let text = "(db) {\n\tport=5432\n}\n";
+    let doc: Document = text.parse().unwrap();
+
+    assert_eq!(doc, Document::parse(text).unwrap());
+    assert_eq!(doc.to_string(), doc.render());
+}
+
+#[test]
+fn from_reader_reads_any_source() {
+    let doc = Document::from_reader(Cursor::new(b"(db) {\n\tport=5432\n}\n")).unwrap();
+
+    assert_eq!(doc.get("db", "port"), Some("5432"));
+}
+
+#[test]
+fn values_may_contain_equals_signs_and_be_empty() {
+    let doc: Document = "(a) {\n\tquery=a=b&c=d\n\tempty=\n\tbare\n}\n".parse().unwrap();
+
+    assert_eq!(doc.get("a", "query"), Some("a=b&c=d"));
+    assert_eq!(doc.get("a", "empty"), Some(""));
+    assert_eq!(doc.get("a", "bare"), Some(""));
+}
+
+#[test]
+fn unbalanced_brackets_are_an_integrity_error() {
+    assert!(matches!(Document::parse("(a) {\n\tx=1\n"), Err(Error::Integrity)));
+    assert!(matches!("(a) {\n}\n}\n".parse::<Document>(), Err(Error::Integrity)));
+}
+
+#[test]
+fn empty_text_is_an_empty_document() {
+    let doc = Document::parse("").unwrap();
+
+    assert!(doc.sections().is_empty());
+    assert_eq!(doc.render(), "");
+}
@@ -0,0 +1,156 @@
+use tnt::{Document, Error, MemoryStorage, Patch, TNT};
+
+#[test]
+fn inverse_of_delete_section_restores_empty_section() {
+    let mut doc: Document = "(a) {\n\tx=1\n}\n(empty) {\n}\n".parse().unwrap();
+    let original = doc.render();
+
+    let inverse = Patch::new().delete_section("empty").apply(&mut doc).unwrap();
+    assert!(doc.section("empty").is_none());
+
+    inverse.apply(&mut doc).unwrap();
+    assert_eq!(doc.render(), original);
+}
+
+#[test]
+fn inverse_of_delete_section_restores_entries_and_markers() {
+    let mut doc: Document = "(db) {\n\thost=h\n\tpassword=!secret hunter2\n}\n".parse().unwrap();
+    let original = doc.render();
+
+    let inverse = Patch::new().delete_section("db").apply(&mut doc).unwrap();
+    inverse.apply(&mut doc).unwrap();
+
+    assert_eq!(doc.render(), original);
+}
+
+#[test]
+fn debug_output_hides_values() {
+    let mut doc: Document = "(db) {\n\thost=h\n\tpassword=!secret hunter2\n}\n".parse().unwrap();
+
+    let inverse = Patch::new().delete_section("db").apply(&mut doc).unwrap();
+    let patch = Patch::new().test("db", "port", Some("5432")).test("db", "user", None).set("db", "port", 6432);
+
+    for text in [format!("{inverse:?}"), format!("{patch:?}")] {
+        assert!(!text.contains("hunter2"));
+        assert!(!text.contains("5432"));
+        assert!(!text.contains("6432"));
+    }
+    assert!(format!("{inverse:?}").contains("password"));
+    assert!(format!("{patch:?}").contains("None"));
+}
+
+#[test]
+fn add_section_fails_when_section_exists() {
+    let mut doc: Document = "(a) {\n}\n".parse().unwrap();
+
+    assert!(matches!(Patch::new().add_section("a").apply(&mut doc), Err(Error::KeyExists(key)) if key == "a"));
+}
+
+#[test]
+fn add_section_round_trips_through_json_and_tnt() {
+    let patch = Patch::new().add_section("a").set("a", "x", 1);
+
+    assert_eq!(Patch::from_json(&patch.to_json()).unwrap(), patch);
+    assert_eq!(Patch::from_tnt(&patch.to_tnt()).unwrap(), patch);
+}
+
+#[test]
+fn failed_test_hides_secret_values() {
+    let mut doc: Document = "(db) {\n\tpassword=!secret hunter2\n\tport=5432\n}\n".parse().unwrap();
+
+    let err = Patch::new().test("db", "password", Some("guess")).apply(&mut doc).unwrap_err();
+    assert!(!err.to_string().contains("hunter2"));
+    assert!(!err.to_string().contains("guess"));
+    assert!(matches!(err, Error::TestFailed { actual: Some(ref val), .. } if val == "***"));
+
+    let err = Patch::new().test("db", "port", Some("6432")).apply(&mut doc).unwrap_err();
+    assert!(matches!(err, Error::TestFailed { actual: Some(ref val), .. } if val == "5432"));
+}
+
+#[test]
+fn failing_operation_leaves_the_document_unchanged() {
+    let mut doc: Document = "(db) {\n\tport=5432\n}\n".parse().unwrap();
+    let original = doc.clone();
+
+    let patch = Patch::new().set("db", "port", 6432).set("db", "host", "h").delete("db", "missing");
+    assert!(matches!(patch.apply(&mut doc), Err(Error::VarNotFound { .. })));
+    assert_eq!(doc, original);
+}
+
+#[test]
+fn test_operation_guards_the_following_operations() {
+    let mut doc: Document = "(db) {\n\tport=5432\n}\n".parse().unwrap();
+
+    let stale = Patch::new().test("db", "port", Some("1")).set("db", "port", 6432);
+    assert!(matches!(stale.apply(&mut doc), Err(Error::TestFailed { .. })));
+    assert_eq!(doc.get("db", "port"), Some("5432"));
+
+    let absent = Patch::new().test("db", "host", None).set("db", "host", "h");
+    absent.apply(&mut doc).unwrap();
+    assert_eq!(doc.get("db", "host"), Some("h"));
+}
+
+#[test]
+fn inverse_patch_restores_every_operation() {
+    let text = "(db) {\n\thost=h\n\tport=5432\n}\n(app) {\n\tname=x\n}\n";
+    let mut doc: Document = text.parse().unwrap();
+
+    let patch = Patch::new()
+        .set("db", "port", 6432)
+        .set("cache", "ttl", 60)
+        .delete("app", "name")
+        .rename_var("db", "host", "address")
+        .move_var("db", "queue", "address")
+        .rename_section("app", "web");
+
+    let inverse = patch.apply(&mut doc).unwrap();
+    assert_eq!(doc.get("db", "port"), Some("6432"));
+    assert_eq!(doc.get("queue", "address"), Some("h"));
+    assert!(doc.section("web").is_some());
+
+    inverse.apply(&mut doc).unwrap();
+    assert_eq!(doc.get("db", "port"), Some("5432"));
+    assert_eq!(doc.get("db", "host"), Some("h"));
+    assert_eq!(doc.get("app", "name"), Some("x"));
+    assert!(doc.section("cache").is_none());
+    assert!(doc.section("queue").is_none());
+    assert!(doc.section("web").is_none());
+}
+
+#[test]
+fn apply_to_file_is_all_or_nothing() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    assert!(tnt.apply(&Patch::new().set("db", "port", 1).rename_section("missing", "x")).is_err());
+    assert_eq!(storage.text(), "(db) {\n\tport=5432\n}\n");
+
+    let inverse = tnt.apply(&Patch::new().set("db", "port", 6432)).unwrap();
+    assert_eq!(storage.text(), "(db) {\n\tport=6432\n}\n");
+
+    tnt.apply(&inverse).unwrap();
+    assert_eq!(storage.text(), "(db) {\n\tport=5432\n}\n");
+}
+
+#[test]
+fn every_operation_round_trips_through_json_and_tnt() {
+    let patch = Patch::new()
+        .set("db", "port", 6432)
+        .delete("db", "host")
+        .add_section("cache")
+        .delete_section("old")
+        .rename_section("app", "web")
+        .rename_var("db", "user", "login")
+        .move_var("db", "queue", "url")
+        .test("db", "port", Some("5432"))
+        .test("db", "pool", None);
+
+    assert_eq!(Patch::from_json(&patch.to_json()).unwrap(), patch);
+    assert_eq!(Patch::from_tnt(&patch.to_tnt()).unwrap(), patch);
+}
+
+#[test]
+fn malformed_patch_text_is_a_parse_error() {
+    assert!(matches!(Patch::from_json("[{\"op\": \"explode\"}]"), Err(Error::Parse(_))));
+    assert!(matches!(Patch::from_json("{"), Err(Error::Parse(_))));
+}
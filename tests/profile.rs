@@ -0,0 +1,68 @@
+mod common;
+
+use common::Scratch;
+use tnt::{Document, MemoryStorage, TNT};
+
+const TEXT: &str = "(db) {\n\thost=localhost\n\tport=5432\n\turl=${db.host}:${db.port}\n}\n\
+(db @prod) {\n\thost=db.prod\n}\n\
+(db @dev) {\n\tport=6543\n}\n\
+(cache @prod) {\n\tttl=60\n}\n";
+
+#[test]
+fn profiles_are_listed_in_order_of_appearance() {
+    let doc: Document = TEXT.parse().unwrap();
+
+    assert_eq!(doc.profiles(), ["prod", "dev"]);
+}
+
+#[test]
+fn profile_values_override_the_base_section() {
+    let tnt = TNT::with_storage(MemoryStorage::new(TEXT));
+    let prod = tnt.with_profile("prod").unwrap();
+
+    assert_eq!(prod.name(), "prod");
+    assert_eq!(prod.get("db", "host").unwrap().as_deref(), Some("db.prod"));
+    assert_eq!(prod.get("db", "port").unwrap().as_deref(), Some("5432"));
+    assert_eq!(prod.get("db", "url").unwrap().as_deref(), Some("db.prod:5432"));
+    assert_eq!(prod.get("cache", "ttl").unwrap().as_deref(), Some("60"));
+}
+
+#[test]
+fn other_profiles_are_not_visible() {
+    let tnt = TNT::with_storage(MemoryStorage::new(TEXT));
+    let dev = tnt.with_profile("dev").unwrap();
+
+    assert_eq!(dev.document().render(), "(db) {\n\thost=localhost\n\tport=6543\n\turl=${db.host}:${db.port}\n}\n");
+    assert!(dev.document().section("cache").is_none());
+
+    let none = tnt.with_profile("staging").unwrap();
+    assert_eq!(none.get("db", "port").unwrap().as_deref(), Some("5432"));
+}
+
+#[test]
+fn profile_exports_are_flattened() {
+    let scratch = Scratch::new("profile-export");
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\tpassword=!secret base\n\thost=h\n}\n(db @prod) {\n\thost=p\n}\n"));
+    let prod = tnt.with_profile("prod").unwrap();
+
+    prod.to_json(&scratch.stem("prod")).unwrap();
+
+    let json = scratch.read("prod.json");
+    assert!(json.contains("\"host\": \"p\""), "{}", json);
+    assert!(json.contains("\"password\": \"***\""), "{}", json);
+    assert!(!json.contains("@prod"), "{}", json);
+}
+
+#[test]
+fn toml_export_of_profile_sections_is_valid_toml() {
+    let scratch = Scratch::new("profile-toml");
+    let tnt = TNT::open(scratch.write("app.tnt", TEXT)).unwrap();
+
+    tnt.to_toml(&scratch.stem("app")).unwrap();
+
+    let toml = scratch.read("app.toml");
+    let table: toml::Table = toml.parse().unwrap_or_else(|e| panic!("{}\n{}", e, toml));
+    assert_eq!(table["db"]["port"].as_str(), Some("5432"));
+    assert_eq!(table["db @prod"]["host"].as_str(), Some("db.prod"));
+    assert_eq!(table["cache @prod"]["ttl"].as_str(), Some("60"));
+}
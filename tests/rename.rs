@@ -0,0 +1,66 @@
+use tnt::{Error, MemoryStorage, TNT};
+
+const TEXT: &str = "(db) {\n\thost=h\n\tport=5432\n}\n(app) {\n\tname=x\n}\n";
+
+#[test]
+fn rename_section_keeps_position_and_contents() {
+    let storage = MemoryStorage::new(TEXT);
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.rename_section("db", "database").unwrap();
+
+    assert_eq!(storage.text(), "(database) {\n\thost=h\n\tport=5432\n}\n(app) {\n\tname=x\n}\n");
+}
+
+#[test]
+fn rename_section_errors() {
+    let storage = MemoryStorage::new(TEXT);
+    let tnt = TNT::with_storage(storage.clone());
+
+    assert!(matches!(tnt.rename_section("missing", "x"), Err(Error::KeyNotFound(key)) if key == "missing"));
+    assert!(matches!(tnt.rename_section("db", "app"), Err(Error::KeyExists(key)) if key == "app"));
+    assert_eq!(storage.text(), TEXT);
+}
+
+#[test]
+fn rename_var_keeps_value_and_position() {
+    let storage = MemoryStorage::new(TEXT);
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.rename_var("db", "host", "address").unwrap();
+
+    assert_eq!(storage.text(), "(db) {\n\taddress=h\n\tport=5432\n}\n(app) {\n\tname=x\n}\n");
+    assert!(matches!(tnt.rename_var("db", "host", "x"), Err(Error::VarNotFound { .. })));
+    assert!(matches!(tnt.rename_var("db", "address", "port"), Err(Error::VarExists { .. })));
+}
+
+#[test]
+fn move_var_appends_to_target_and_creates_it() {
+    let storage = MemoryStorage::new(TEXT);
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.move_var("db", "app", "host").unwrap();
+    tnt.move_var("db", "net", "port").unwrap();
+
+    assert_eq!(storage.text(), "(db) {\n}\n(app) {\n\tname=x\n\thost=h\n}\n(net) {\n\tport=5432\n}\n");
+}
+
+#[test]
+fn move_var_errors() {
+    let storage = MemoryStorage::new(TEXT);
+    let tnt = TNT::with_storage(storage.clone());
+    tnt.set("app", "host", "other").unwrap();
+
+    assert!(matches!(tnt.move_var("db", "app", "missing"), Err(Error::VarNotFound { .. })));
+    assert!(matches!(tnt.move_var("db", "app", "host"), Err(Error::VarExists { .. })));
+    assert_eq!(tnt.get("db", "host").unwrap(), "h");
+}
+
+#[test]
+fn broken_file_is_an_integrity_error() {
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\thost=h\n"));
+
+    assert!(matches!(tnt.rename_section("db", "x"), Err(Error::Integrity)));
+    assert!(matches!(tnt.rename_var("db", "host", "x"), Err(Error::Integrity)));
+    assert!(matches!(tnt.move_var("db", "x", "host"), Err(Error::Integrity)));
+}
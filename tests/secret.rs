@@ -0,0 +1,125 @@
+mod common;
+
+use common::Scratch;
+use tnt::{Document, MemoryStorage, TNT, secret};
+
+#[test]
+fn json_export_handles_empty_section_and_empty_file() {
+    let scratch = Scratch::new("secret-json-empty");
+
+    let tnt = TNT::with_storage(MemoryStorage::new("(a) {\n}\n(b) {\n\tx=1\n}\n"));
+    tnt.to_json(&scratch.stem("sections")).unwrap();
+    tnt.to_json_revealed(&scratch.stem("revealed")).unwrap();
+    assert_eq!(scratch.read("sections.json"), "{\n\t\"a\": {\n\t},\n\n\t\"b\": {\n\t\t\"x\": \"1\"\n\t}\n}\n");
+    assert_eq!(scratch.read("revealed.json"), scratch.read("sections.json"));
+
+    let tnt = TNT::with_storage(MemoryStorage::new(""));
+    tnt.to_json_resolved(&scratch.stem("empty")).unwrap();
+    assert_eq!(scratch.read("empty.json"), "{}\n");
+}
+
+#[test]
+fn resolved_export_hides_values_built_from_secrets() {
+    let scratch = Scratch::new("secret-resolved");
+    let text = "(db) {\n\tpassword=!secret hunter2\n\turl=pg://u:${db.password}@h\n\tvia=${db.url}\n\thost=h\n}\n";
+    let tnt = TNT::with_storage(MemoryStorage::new(text));
+
+    tnt.to_json_resolved(&scratch.stem("resolved")).unwrap();
+    tnt.to_toml_resolved(&scratch.stem("resolved")).unwrap();
+
+    for file in ["resolved.json", "resolved.toml"] {
+        let out = scratch.read(file);
+        assert!(!out.contains("hunter2"), "{}: {}", file, out);
+        assert!(out.contains("h\""), "{}: {}", file, out);
+    }
+
+    let doc = tnt.load().unwrap().resolved().unwrap();
+    assert!(doc.is_secret("db", "url"));
+    assert!(doc.is_secret("db", "via"));
+    assert!(!doc.is_secret("db", "host"));
+    assert_eq!(doc.get("db", "url"), Some("pg://u:hunter2@h"));
+}
+
+#[test]
+fn marked_and_conventional_secrets_are_hidden_in_exports() {
+    let scratch = Scratch::new("secret-export");
+    let tnt = TNT::with_storage(MemoryStorage::new("(db) {\n\tpassword=hunter2\n\tdsn=!secret pg://u:p@h\n\thost=h\n}\n"));
+
+    tnt.to_json(&scratch.stem("hidden")).unwrap();
+    tnt.to_json_revealed(&scratch.stem("revealed")).unwrap();
+
+    let hidden = scratch.read("hidden.json");
+    assert!(!hidden.contains("hunter2") && !hidden.contains("pg://"), "{}", hidden);
+    assert!(hidden.contains("\"password\": \"***\""));
+
+    let revealed = scratch.read("revealed.json");
+    assert!(revealed.contains("hunter2") && revealed.contains("\"dsn\": \"pg://u:p@h\""), "{}", revealed);
+}
+
+#[test]
+fn get_returns_the_value_without_the_marker() {
+    let storage = MemoryStorage::new("(db) {\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.set_secret("db", "dsn", "pg://h").unwrap();
+
+    assert_eq!(storage.text(), "(db) {\n\tdsn=!secret pg://h\n}\n");
+    assert_eq!(tnt.get("db", "dsn").unwrap(), "pg://h");
+    assert!(tnt.load().unwrap().is_secret("db", "dsn"));
+}
+
+#[test]
+fn debug_output_redacts_secrets() {
+    let doc: Document = "(db) {\n\tdsn=!secret pg://u:p@h\n\ttoken=abc123\n\thost=visible\n}\n".parse().unwrap();
+    let debug = format!("{:?}", doc);
+
+    assert!(!debug.contains("pg://") && !debug.contains("abc123"), "{}", debug);
+    assert!(debug.contains("visible"));
+}
+
+#[test]
+fn secret_names_follow_the_convention() {
+    for name in ["password", "DB_PASSWORD", "api_key", "github_token", "private_key", "client_secret"] {
+        assert!(secret::is_secret_name(name), "{}", name);
+    }
+
+    for name in ["host", "port", "user", "keyboard"] {
+        assert!(!secret::is_secret_name(name), "{}", name);
+    }
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypted_values_round_trip() {
+    use tnt::{Error, SecretKey};
+
+    let scratch = Scratch::new("secret-encrypted");
+    let key = SecretKey::generate(scratch.path("key")).unwrap();
+    let storage = MemoryStorage::new("(db) {\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.set_encrypted("db", "password", "hunter2", &key).unwrap();
+
+    assert!(storage.text().starts_with("(db) {\n\tpassword=!secret enc:"));
+    assert!(!storage.text().contains("hunter2"));
+    assert_eq!(tnt.get_decrypted("db", "password", &key).unwrap().as_deref(), Some("hunter2"));
+
+    let loaded = SecretKey::load(scratch.path("key")).unwrap();
+    assert_eq!(tnt.get_decrypted("db", "password", &loaded).unwrap().as_deref(), Some("hunter2"));
+
+    let other = SecretKey::generate(scratch.path("other")).unwrap();
+    assert!(matches!(tnt.get_decrypted("db", "password", &other), Err(Error::Crypto(_))));
+    assert!(SecretKey::generate(scratch.path("key")).is_err());
+    assert_eq!(key.decrypt("plain").unwrap(), "plain");
+}
+
+#[cfg(all(feature = "encryption", unix))]
+#[test]
+fn generated_key_file_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let scratch = Scratch::new("secret-key-mode");
+    tnt::SecretKey::generate(scratch.path("key")).unwrap();
+
+    assert_eq!(std::fs::metadata(scratch.path("key")).unwrap().permissions().mode() & 0o777, 0o600);
+}
@@ -0,0 +1,84 @@
+use tnt::{Document, Error, MemoryStorage, TNT};
+
+#[test]
+fn section_handles_read_and_write_variables() {
+    let mut doc: Document = "(db) {\n\thost=h\n}\n".parse().unwrap();
+
+    let db = doc.section_mut("db");
+    db.set("port", 5432).unwrap();
+    db.set("host", "localhost").unwrap();
+    assert_eq!(db.remove("missing"), None);
+
+    let db = doc.section("db").unwrap();
+    assert_eq!(db.name(), "db");
+    assert_eq!(db.len(), 2);
+    assert_eq!(db.get("host"), Some("localhost"));
+    assert_eq!(db.iter().collect::<Vec<_>>(), [("host", "localhost"), ("port", "5432")]);
+    assert!(doc.section("missing").is_none());
+}
+
+#[test]
+fn section_mut_creates_missing_sections_at_the_end() {
+    let mut doc: Document = "(a) {\n}\n".parse().unwrap();
+
+    assert!(doc.section_mut("b").is_empty());
+    doc.section_mut("b").set("x", 1).unwrap();
+
+    assert_eq!(doc.render(), "(a) {\n}\n(b) {\n\tx=1\n}\n");
+}
+
+#[test]
+fn insert_before_and_after_control_order() {
+    let mut doc = Document::new();
+    let db = doc.section_mut("db");
+    db.set("a", 1).unwrap();
+    db.set("c", 3).unwrap();
+
+    db.insert_before("c", "b", 2).unwrap();
+    db.insert_after("c", "d", 4).unwrap();
+    db.insert_before("a", "d", 0).unwrap();
+
+    assert_eq!(db.iter().collect::<Vec<_>>(), [("d", "0"), ("a", "1"), ("b", "2"), ("c", "3")]);
+    assert!(matches!(db.insert_after("missing", "x", 1), Err(Error::VarNotFound { .. })));
+}
+
+#[test]
+fn entry_api_inserts_and_modifies() {
+    let mut doc: Document = "(db) {\n\tport=5432\n}\n".parse().unwrap();
+    let db = doc.section_mut("db");
+
+    assert_eq!(db.entry("port").key(), "port");
+    assert_eq!(db.entry("port").or_insert("1"), "5432");
+    assert_eq!(db.entry("host").or_insert_with(|| "h"), "h");
+    assert_eq!(db.entry("user").or_default(), "");
+    db.entry("port").and_modify(|val| val.push('0')).or_insert("1");
+    db.entry("pool").and_modify(|val| val.push('0')).or_insert("10");
+    *db.entry("user").or_default() = "admin".to_string();
+
+    assert_eq!(doc.render(), "(db) {\n\tport=54320\n\thost=h\n\tuser=admin\n\tpool=10\n}\n");
+}
+
+#[test]
+fn removing_a_secret_clears_its_marker() {
+    let mut doc: Document = "(db) {\n\tdsn=!secret hunter2\n}\n".parse().unwrap();
+    let db = doc.section_mut("db");
+
+    assert!(db.is_secret("dsn"));
+    assert_eq!(db.remove("dsn").as_deref(), Some("hunter2"));
+    db.set("dsn", "plain").unwrap();
+
+    assert!(!doc.is_secret("db", "dsn"));
+    assert_eq!(doc.render(), "(db) {\n\tdsn=plain\n}\n");
+}
+
+#[test]
+fn edited_document_saves_back() {
+    let storage = MemoryStorage::new("(db) {\n\thost=h\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    let mut doc = tnt.load().unwrap();
+    doc.section_mut("db").insert_before("host", "port", 5432).unwrap();
+    tnt.save(&doc).unwrap();
+
+    assert_eq!(storage.text(), "(db) {\n\tport=5432\n\thost=h\n}\n");
+}
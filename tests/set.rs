@@ -0,0 +1,82 @@
+use tnt::{Document, Error, MemoryStorage, TNT};
+
+#[test]
+fn set_edits_existing_and_adds_missing_variables() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.set("db", "port", 6432).unwrap();
+    tnt.set("db", "host", "h").unwrap();
+    tnt.set("app", "name", "x").unwrap();
+
+    assert_eq!(storage.text(), "(db) {\n\tport=6432\n\thost=h\n}\n(app) {\n\tname=x\n}\n");
+}
+
+#[test]
+fn set_many_applies_every_item_and_last_repeat_wins() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.set_many(&[("db", "port", "1"), ("app", "name", "x"), ("db", "port", "2"), ("app", "debug", "true")]).unwrap();
+
+    assert_eq!(storage.text(), "(db) {\n\tport=2\n}\n(app) {\n\tname=x\n\tdebug=true\n}\n");
+}
+
+#[test]
+fn set_keeps_the_secret_marker() {
+    let storage = MemoryStorage::new("(db) {\n\tpassword=!secret old\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.set("db", "password", "new").unwrap();
+
+    assert_eq!(storage.text(), "(db) {\n\tpassword=!secret new\n}\n");
+    assert_eq!(tnt.get("db", "password").unwrap(), "new");
+}
+
+#[test]
+fn set_trims_names() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    tnt.set(" db ", " port ", 1).unwrap();
+
+    assert_eq!(storage.text(), "(db) {\n\tport=1\n}\n");
+}
+
+#[test]
+fn set_on_a_broken_file_is_an_integrity_error() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    assert!(matches!(tnt.set("db", "port", 1), Err(Error::Integrity)));
+    assert!(matches!(tnt.set_many(&[("db", "port", 1)]), Err(Error::Integrity)));
+    assert_eq!(storage.text(), "(db) {\n\tport=5432\n");
+}
+
+#[test]
+fn set_rejects_names_and_values_that_break_the_markup() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+
+    assert!(matches!(tnt.set("db", "port", "1\n}\n(evil) {"), Err(Error::Parse(_))));
+    assert!(matches!(tnt.set("db", "port", "}"), Err(Error::Parse(_))));
+    assert!(matches!(tnt.set("db", "a=b", 1), Err(Error::Parse(_))));
+    assert!(matches!(tnt.set("db", "a{", 1), Err(Error::Parse(_))));
+    assert!(matches!(tnt.set("x) {", "a", 1), Err(Error::Parse(_))));
+    assert!(matches!(tnt.set_many(&[("db", "port", "1"), ("db", "host", "h\n")]), Err(Error::Parse(_))));
+    assert_eq!(storage.text(), "(db) {\n\tport=5432\n}\n");
+
+    tnt.set("db", "url", "pg://${db.host}/f(x)").unwrap();
+    assert_eq!(tnt.get_raw("db", "url").unwrap(), "pg://${db.host}/f(x)");
+}
+
+#[test]
+fn document_and_section_set_reject_the_same_input() {
+    let mut doc = Document::new();
+
+    assert!(matches!(doc.set("db", "port", "1\n(evil) {"), Err(Error::Parse(_))));
+    assert!(matches!(doc.set("(db", "port", 1), Err(Error::Parse(_))));
+    assert!(matches!(doc.section_mut("db").set("a=b", 1), Err(Error::Parse(_))));
+    assert!(doc.section("db").unwrap().is_empty());
+    assert_eq!(doc.render(), "(db) {\n}\n");
+}
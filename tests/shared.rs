@@ -0,0 +1,66 @@
+use std::thread;
+
+use tnt::{Error, MemoryStorage, TNT, WritableStorage};
+
+#[test]
+fn snapshots_stay_unchanged_after_updates() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let config = TNT::with_storage(storage.clone()).shared().unwrap();
+
+    let before = config.snapshot();
+    assert_eq!(config.set("db", "port", 6432).unwrap(), 1);
+
+    assert_eq!(before.version(), 0);
+    assert_eq!(before.get("db", "port"), Some("5432"));
+    assert_eq!(config.snapshot().document().get("db", "port"), Some("6432"));
+    assert_eq!(storage.text(), "(db) {\n\tport=6432\n}\n");
+}
+
+#[test]
+fn concurrent_updates_are_serialized() {
+    let config = TNT::with_storage(MemoryStorage::new("(counter) {\n\tn=0\n}\n")).shared().unwrap();
+
+    thread::scope(|s| {
+        for _ in 0..8 {
+            let config = config.clone();
+            s.spawn(move || {
+                for _ in 0..25 {
+                    config
+                        .update(|doc| {
+                            let n: u64 = doc.get("counter", "n").unwrap().parse().unwrap();
+                            doc.set("counter", "n", n + 1)
+                        })
+                        .unwrap();
+                }
+            });
+        }
+    });
+
+    assert_eq!(config.version(), 200);
+    assert_eq!(config.snapshot().get("counter", "n"), Some("200"));
+}
+
+#[test]
+fn reload_publishes_external_changes() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let config = TNT::with_storage(storage.clone()).shared().unwrap();
+
+    assert_eq!(config.reload().unwrap(), 0);
+
+    storage.write("(db) {\n\tport=7432\n}\n").unwrap();
+    assert_eq!(config.reload().unwrap(), 1);
+    assert_eq!(config.snapshot().get("db", "port"), Some("7432"));
+}
+
+#[test]
+fn failed_save_keeps_the_previous_snapshot() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let config = TNT::with_storage(storage.clone()).shared().unwrap();
+
+    storage.write("(db) {\n\tport=7432\n}\n").unwrap();
+
+    assert!(matches!(config.set("db", "port", 6432), Err(Error::Conflict(_))));
+    assert_eq!(config.version(), 0);
+    assert_eq!(config.snapshot().get("db", "port"), Some("5432"));
+    assert_eq!(storage.text(), "(db) {\n\tport=7432\n}\n");
+}
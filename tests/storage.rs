@@ -0,0 +1,152 @@
+mod common;
+
+use std::fs;
+use std::io;
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use common::Scratch;
+use tnt::{FileStorage, MemoryStorage, ReadOnly, Storage, TNT, WritableStorage};
+
+#[test]
+fn concurrent_writes_never_leave_a_partial_file() {
+    let scratch = Scratch::new("storage-concurrent");
+    let path = scratch.write("app.tnt", "");
+
+    let texts: Vec<String> = (0..8).map(|i| format!("(w{}) {{\n{}}}\n", i, "\tx=0123456789\n".repeat(2000))).collect();
+    let texts = &texts;
+
+    thread::scope(|s| {
+        for text in texts {
+            let storage = FileStorage::new(&path);
+            s.spawn(move || {
+                for _ in 0..20 {
+                    storage.write(text).unwrap();
+                    assert!(texts.contains(&storage.read().unwrap()), "partial write observed");
+                }
+            });
+        }
+    });
+
+    let current = fs::read_to_string(&path).unwrap();
+    assert!(texts.contains(&current));
+
+    let leftovers: Vec<_> = fs::read_dir(scratch.dir()).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(leftovers, ["app.tnt"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn write_keeps_permissions_and_symlinks() {
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    let scratch = Scratch::new("storage-unix");
+    let real = scratch.write("real.tnt", "(a) {\n}\n");
+    fs::set_permissions(&real, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let link = scratch.path("link.tnt");
+    symlink(&real, &link).unwrap();
+
+    FileStorage::new(&link).write("(b) {\n}\n").unwrap();
+
+    assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_to_string(&real).unwrap(), "(b) {\n}\n");
+    assert_eq!(fs::metadata(&real).unwrap().permissions().mode() & 0o777, 0o600);
+}
+
+/// A custom store keeping every written version, as an external blob store might.
+#[derive(Default)]
+struct Versioned {
+    versions: Mutex<Vec<String>>,
+}
+
+impl Storage for Versioned {
+    fn read(&self) -> io::Result<String> {
+        Ok(self.versions.lock().unwrap().last().cloned().unwrap_or_default())
+    }
+}
+
+impl WritableStorage for Versioned {
+    fn write(&self, text: &str) -> io::Result<()> {
+        self.versions.lock().unwrap().push(text.to_string());
+        Ok(())
+    }
+}
+
+#[test]
+fn tnt_works_over_a_custom_storage() {
+    let tnt = TNT::with_storage(Versioned::default());
+
+    tnt.set("db", "port", 5432).unwrap();
+    tnt.set("db", "port", 6432).unwrap();
+
+    assert_eq!(tnt.get("db", "port").unwrap(), "6432");
+    assert_eq!(tnt.storage().versions.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn memory_storage_clones_share_text() {
+    let storage = MemoryStorage::new("(a) {\n}\n");
+    let clone = storage.clone();
+
+    TNT::with_storage(clone).set("a", "x", 1).unwrap();
+
+    assert_eq!(storage.text(), "(a) {\n\tx=1\n}\n");
+    assert_eq!(storage.stamp().unwrap(), None);
+}
+
+#[test]
+fn memory_storage_debug_does_not_print_the_text() {
+    let storage = MemoryStorage::new("(db) {\n\tpassword=hunter2\n\tdsn=!secret s3cr3t\n}\n");
+
+    for debug in [format!("{:?}", storage), format!("{:?}", TNT::with_storage(storage.clone()))] {
+        assert!(!debug.contains("hunter2"), "{}", debug);
+        assert!(!debug.contains("s3cr3t"), "{}", debug);
+    }
+}
+
+#[test]
+fn memory_storage_lock_is_exclusive() {
+    let storage = MemoryStorage::new("");
+    let (tx, rx) = mpsc::channel();
+    storage.lock().unwrap();
+
+    let other = storage.clone();
+    let handle = thread::spawn(move || {
+        other.lock().unwrap();
+        tx.send(()).unwrap();
+        other.unlock().unwrap();
+    });
+
+    assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    storage.unlock().unwrap();
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn read_only_passes_reads_through() {
+    let scratch = Scratch::new("storage-read-only");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+
+    let tnt = TNT::with_storage(ReadOnly::new(FileStorage::new(&path)));
+
+    assert_eq!(tnt.get("db", "port").unwrap(), "5432");
+    assert_eq!(tnt.storage().stamp().unwrap(), FileStorage::new(&path).stamp().unwrap());
+    assert_eq!(tnt.storage().inner().filename(), path.to_str().unwrap());
+}
+
+#[test]
+fn file_storage_reports_missing_files_and_stamps() {
+    let scratch = Scratch::new("storage-file");
+    let storage = FileStorage::new(scratch.path("app.tnt"));
+
+    assert_eq!(storage.read().unwrap_err().kind(), io::ErrorKind::NotFound);
+
+    storage.write("(a) {\n}\n").unwrap();
+    let (_, len) = storage.stamp().unwrap().unwrap();
+
+    assert_eq!(len, 8);
+    assert_eq!(storage.read().unwrap(), "(a) {\n}\n");
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+
+use common::Scratch;
+use tnt::{Change, MemoryStorage, TNT, WritableStorage};
+
+#[test]
+fn subscriber_can_read_snapshot_while_notified() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::with_storage(storage.clone());
+    let mut watcher = tnt.watch().unwrap().interval(Duration::from_millis(10));
+
+    let (notified_tx, notified_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+
+    watcher.subscribe(move |_, _| {
+        notified_tx.send(()).unwrap();
+        release_rx.recv().unwrap();
+    });
+
+    let handle = watcher.spawn();
+    tnt.set("db", "port", 6432).unwrap();
+
+    notified_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(handle.snapshot().get("db", "port"), Some("6432"));
+
+    release_tx.send(()).unwrap();
+    handle.stop();
+}
+
+#[test]
+fn poll_reports_changes_and_notifies_subscribers() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let mut watcher = TNT::with_storage(storage.clone()).watch().unwrap();
+
+    let seen: Arc<Mutex<Vec<Change>>> = Arc::default();
+    let sink = Arc::clone(&seen);
+    watcher.subscribe(move |changes, doc| {
+        assert_eq!(doc.get("db", "port"), Some("6432"));
+        sink.lock().unwrap().extend_from_slice(changes);
+    });
+
+    assert!(watcher.poll().unwrap().is_empty());
+    storage.write("(db) {\n\tport=6432\n}\n").unwrap();
+
+    let expected = [Change::VarChanged { key: "db".into(), var: "port".into(), old: "5432".into(), new: "6432".into() }];
+    assert_eq!(watcher.poll().unwrap(), expected);
+    assert_eq!(*seen.lock().unwrap(), expected);
+    assert_eq!(watcher.snapshot().get("db", "port"), Some("6432"));
+}
+
+#[test]
+fn unchanged_text_does_not_notify() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let mut watcher = TNT::with_storage(storage.clone()).watch().unwrap();
+
+    let calls = Arc::new(Mutex::new(0));
+    let sink = Arc::clone(&calls);
+    watcher.subscribe(move |_, _| *sink.lock().unwrap() += 1);
+
+    storage.write("(db) {\n\tport=5432\n}\n").unwrap();
+    assert!(watcher.poll().unwrap().is_empty());
+    assert_eq!(*calls.lock().unwrap(), 0);
+}
+
+#[test]
+fn invalid_edit_keeps_the_last_good_snapshot() {
+    let storage = MemoryStorage::new("(db) {\n\tport=5432\n}\n");
+    let mut watcher = TNT::with_storage(storage.clone()).watch().unwrap();
+
+    storage.write("(db) {\n\tport=6432\n").unwrap();
+    assert!(watcher.poll().is_err());
+    assert_eq!(watcher.snapshot().get("db", "port"), Some("5432"));
+
+    storage.write("(db) {\n\tport=7432\n}\n").unwrap();
+    assert_eq!(watcher.poll().unwrap().len(), 1);
+    assert_eq!(watcher.snapshot().get("db", "port"), Some("7432"));
+}
+
+#[test]
+fn watch_fails_on_an_invalid_file() {
+    assert!(TNT::with_storage(MemoryStorage::new("(db) {\n")).watch().is_err());
+}
+
+#[test]
+fn spawned_watcher_picks_up_file_edits() {
+    let scratch = Scratch::new("watch-file");
+    let path = scratch.write("app.tnt", "(db) {\n\tport=5432\n}\n");
+    let tnt = TNT::open(&path).unwrap();
+
+    let mut watcher = tnt.watch().unwrap().interval(Duration::from_millis(10));
+    let (tx, rx) = mpsc::channel();
+    watcher.subscribe(move |changes, _| tx.send(changes.to_vec()).unwrap());
+    let handle = watcher.spawn();
+
+    scratch.write("app.tnt", "(db) {\n\tport=5432\n\thost=h\n}\n");
+
+    let changes = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(changes, [Change::VarAdded { key: "db".into(), var: "host".into(), val: "h".into() }]);
+    assert_eq!(handle.snapshot().get("db", "host"), Some("h"));
+    handle.stop();
+}